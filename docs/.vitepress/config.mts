@@ -67,6 +67,7 @@ export default defineConfig({
         text: "API Reference",
         items: [
           { text: "WebView", link: "/reference/webview" },
+          { text: "WebViewPromise", link: "/reference/webview-promise" },
           { text: "JavaScript", link: "/reference/javascript" },
         ],
       },
@@ -0,0 +1,240 @@
+// Injected into every page. Exposes the `window.godot` object, built on top
+// of `window.ipc.postMessage` for page -> Godot and `evaluate_script` for the replies.
+pub const BRIDGE_SCRIPT: &str = r#"
+    (() => {
+        if (window.godot && window.godot._bridge) return;
+        const godot = window.godot = window.godot || {};
+        const pending = new Map();
+        let nextId = 0;
+
+        Object.defineProperty(godot, '_bridge', { value: true });
+
+        godot.call = (method, ...args) => new Promise((resolve, reject) => {
+            const id = ++nextId;
+            pending.set(id, { resolve, reject });
+            window.ipc.postMessage(JSON.stringify({
+                type: '_rpc_call',
+                id,
+                method,
+                args
+            }));
+        });
+
+        godot.functions = {};
+
+        godot._setFunctions = (names) => {
+            for (const name of Object.keys(godot.functions)) {
+                if (!names.includes(name)) delete godot.functions[name];
+            }
+            for (const name of names) {
+                godot.functions[name] = (...args) => godot.call(name, ...args);
+            }
+        };
+
+        const listeners = new Map();
+
+        godot.on = (channel, handler) => {
+            if (!listeners.has(channel)) listeners.set(channel, new Set());
+            listeners.get(channel).add(handler);
+            return () => godot.off(channel, handler);
+        };
+
+        godot.off = (channel, handler) => {
+            listeners.get(channel)?.delete(handler);
+        };
+
+        godot.emit = (channel, payload) => {
+            window.ipc.postMessage(JSON.stringify({
+                type: '_event',
+                channel,
+                payload
+            }));
+        };
+
+        godot._dispatch = (channel, payload) => {
+            for (const handler of listeners.get(channel) ?? []) {
+                try {
+                    handler(payload);
+                } catch (e) {
+                    console.error(e);
+                }
+            }
+        };
+
+        const byteListeners = new Map();
+
+        godot.sendBytes = async (channel, data) => {
+            await fetch(`${godot._bytesUrl}/send/${encodeURIComponent(channel)}`, {
+                method: 'POST',
+                body: data
+            });
+        };
+
+        godot.onBytes = (channel, handler) => {
+            if (!byteListeners.has(channel)) byteListeners.set(channel, new Set());
+            byteListeners.get(channel).add(handler);
+            return () => byteListeners.get(channel)?.delete(handler);
+        };
+
+        godot._receiveBytes = async (channel, id) => {
+            const response = await fetch(`${godot._bytesUrl}/take/${id}`);
+            const data = await response.arrayBuffer();
+            for (const handler of byteListeners.get(channel) ?? []) {
+                try {
+                    handler(data);
+                } catch (e) {
+                    console.error(e);
+                }
+            }
+        };
+
+        const splitPath = (path) => String(path ?? '').split('.').filter(Boolean);
+        let state = {};
+
+        godot.state = new EventTarget();
+
+        godot.state.get = (path) => splitPath(path).reduce((node, key) => node?.[key], state);
+
+        godot.state.set = (path, value) => {
+            applyState(path, value, 'page');
+            window.ipc.postMessage(JSON.stringify({
+                type: '_state_set',
+                path,
+                value
+            }));
+        };
+
+        godot.state.on = (path, handler) => {
+            const listener = (e) => {
+                const changed = splitPath(e.detail.path).join('.');
+                const watched = splitPath(path).join('.');
+                // compared by segments, watching "player" ignores "players.count"
+                const within = (path, parent) => parent === '' || path === parent || path.startsWith(parent + '.');
+                if (within(changed, watched) || within(watched, changed)) {
+                    handler(godot.state.get(path), e.detail);
+                }
+            };
+            godot.state.addEventListener('change', listener);
+            return () => godot.state.removeEventListener('change', listener);
+        };
+
+        const applyState = (path, value, source) => {
+            const keys = splitPath(path);
+            if (keys.length === 0) {
+                state = value ?? {};
+            } else {
+                let node = state;
+                for (const key of keys.slice(0, -1)) {
+                    if (typeof node[key] !== 'object' || node[key] === null) node[key] = {};
+                    node = node[key];
+                }
+                node[keys[keys.length - 1]] = value;
+            }
+            godot.state.dispatchEvent(new CustomEvent('change', {
+                detail: { path: keys.join('.'), value, source }
+            }));
+        };
+
+        godot.state._apply = (path, value) => applyState(path, value, 'godot');
+
+        const jsonRpcHandlers = new Set();
+
+        godot.jsonRpc = {
+            send: (message) => {
+                window.ipc.postMessage(typeof message === 'string' ? message : JSON.stringify(message));
+            },
+            onResponse: (handler) => {
+                jsonRpcHandlers.add(handler);
+                return () => jsonRpcHandlers.delete(handler);
+            }
+        };
+
+        godot._receiveJsonRpc = (response) => {
+            for (const handler of jsonRpcHandlers) {
+                try {
+                    handler(response);
+                } catch (e) {
+                    console.error(e);
+                }
+            }
+        };
+
+        godot._invoke = async (id, path, args) => {
+            let message;
+            try {
+                const keys = path.split('.');
+                const name = keys.pop();
+                const owner = keys.reduce((node, key) => node?.[key], window);
+                if (typeof owner?.[name] !== 'function') {
+                    throw new TypeError(`${path} is not a function`);
+                }
+                const value = await owner[name](...args);
+                message = JSON.stringify({ type: '_js_result', id, ok: true, value: value ?? null });
+            } catch (e) {
+                message = JSON.stringify({ type: '_js_result', id, ok: false, error: String(e) });
+            }
+            window.ipc.postMessage(message);
+        };
+
+        godot._settle = (id, ok, value) => {
+            const call = pending.get(id);
+            if (!call) return;
+            pending.delete(id);
+            if (ok) {
+                call.resolve(value);
+            } else {
+                call.reject(new Error(value));
+            }
+        };
+    })();
+"#;
+
+// Injected when `forward_console` is enabled. Mirrors console output and
+// uncaught errors to Godot as `_console` messages.
+pub const CONSOLE_SCRIPT: &str = r#"
+    (() => {
+        const format = (args) => args.map((arg) => {
+            if (typeof arg === 'string') return arg;
+            if (arg instanceof Error) return arg.stack || String(arg);
+            try {
+                return JSON.stringify(arg) ?? String(arg);
+            } catch {
+                return String(arg);
+            }
+        }).join(' ');
+
+        const send = (level, message, source, line) => {
+            window.ipc.postMessage(JSON.stringify({
+                type: '_console',
+                level,
+                message,
+                source: source || '',
+                line: line || 0
+            }));
+        };
+
+        // location of the function calling the one that created `error`, from
+        // "at fn (url:line:col)" (Chromium) or "fn@url:line:col" (WebKit) frames
+        const callerLocation = (error) => {
+            const frames = String(error.stack || '').split('\n');
+            if (!/^\s*at |@/.test(frames[0])) frames.shift();
+            const match = /([^\s(@]+):(\d+):\d+\)?\s*$/.exec(frames[1] || '');
+            return match ? [match[1], Number(match[2])] : ['', 0];
+        };
+
+        for (const level of ['debug', 'log', 'info', 'warn', 'error']) {
+            const original = console[level];
+            console[level] = (...args) => {
+                original.apply(console, args);
+                send(level, format(args), ...callerLocation(new Error()));
+            };
+        }
+
+        window.addEventListener('error', (e) => {
+            send('error', e.message, e.filename, e.lineno);
+        });
+        window.addEventListener('unhandledrejection', (e) => {
+            send('error', 'Uncaught (in promise) ' + format([e.reason]));
+        });
+    })();
+"#;
@@ -0,0 +1,175 @@
+// HTTP caching (RFC 7232) for files served by the `res` protocol.
+
+use crate::glob::glob_match;
+
+// Used when no `cache_control` pattern matches: the page may keep the
+// file, but has to revalidate it, which is cheap thanks to 304 responses.
+pub const DEFAULT_CACHE_CONTROL: &str = "no-cache";
+
+const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+// Patterns are tried in order, the first match wins.
+pub fn cache_control_for<'a>(path: &str, policies: &'a [(String, String)]) -> &'a str {
+    policies
+        .iter()
+        .find(|(pattern, _)| glob_match(pattern, path))
+        .map(|(_, value)| value.as_str())
+        .unwrap_or(DEFAULT_CACHE_CONTROL)
+}
+
+// Whether a GET/HEAD request can be answered with 304 Not Modified.
+// `If-None-Match` takes precedence over `If-Modified-Since`.
+pub fn is_not_modified(
+    if_none_match: Option<&str>,
+    if_modified_since: Option<&str>,
+    etag: &str,
+    last_modified: Option<u64>,
+) -> bool {
+    if let Some(if_none_match) = if_none_match {
+        return if_none_match.trim() == "*"
+            || if_none_match.split(',').any(|tag| weak_etag_eq(tag.trim(), etag));
+    }
+
+    match (if_modified_since.and_then(parse_http_date), last_modified) {
+        (Some(since), Some(modified)) => modified <= since,
+        _ => false,
+    }
+}
+
+fn weak_etag_eq(a: &str, b: &str) -> bool {
+    let a = a.strip_prefix("W/").unwrap_or(a);
+    let b = b.strip_prefix("W/").unwrap_or(b);
+    a == b
+}
+
+// IMF-fixdate, e.g. "Sun, 06 Nov 1994 08:49:37 GMT"
+pub fn format_http_date(unix_time: u64) -> String {
+    let days = unix_time / 86400;
+    let seconds = unix_time % 86400;
+    let (year, month, day) = civil_from_days(days as i64);
+
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        DAYS[(days % 7) as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+// Only IMF-fixdate is supported, the obsolete formats are treated as invalid dates.
+pub fn parse_http_date(date: &str) -> Option<u64> {
+    let parts: Vec<&str> = date.split_whitespace().collect();
+    let [_, day, month, year, time, "GMT"] = parts.as_slice() else {
+        return None;
+    };
+
+    let day = day.parse::<u32>().ok().filter(|day| (1..=31).contains(day))?;
+    let month = MONTHS.iter().position(|m| m == month)? as u32 + 1;
+    let year = year.parse::<i64>().ok().filter(|year| *year >= 1970)?;
+
+    let time: Vec<u64> = time.split(':').map(|part| part.parse::<u64>().ok()).collect::<Option<_>>()?;
+    let [hours, minutes, seconds] = time.as_slice() else {
+        return None;
+    };
+    if *hours > 23 || *minutes > 59 || *seconds > 60 {
+        return None;
+    }
+
+    let days = u64::try_from(days_from_civil(year, month, day)).ok()?;
+    Some(days * 86400 + hours * 3600 + minutes * 60 + seconds)
+}
+
+// Conversions between days since 1970-01-01 and the proleptic Gregorian calendar,
+// from http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = (if month_index < 10 { month_index + 3 } else { month_index - 9 }) as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_http_dates() {
+        assert_eq!(format_http_date(0), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(format_http_date(784111777), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(format_http_date(951782400), "Tue, 29 Feb 2000 00:00:00 GMT");
+    }
+
+    #[test]
+    fn parses_http_dates() {
+        assert_eq!(parse_http_date("Thu, 01 Jan 1970 00:00:00 GMT"), Some(0));
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), Some(784111777));
+        assert_eq!(parse_http_date(&format_http_date(1700000000)), Some(1700000000));
+    }
+
+    #[test]
+    fn rejects_invalid_dates() {
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sun Nov  6 08:49:37 1994"), None);
+        assert_eq!(parse_http_date("Sun, 06 Foo 1994 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 25:49:37 GMT"), None);
+        assert_eq!(parse_http_date(""), None);
+    }
+
+    #[test]
+    fn if_none_match() {
+        assert!(is_not_modified(Some("\"a\""), None, "\"a\"", None));
+        assert!(is_not_modified(Some("\"b\", \"a\""), None, "\"a\"", None));
+        assert!(is_not_modified(Some("W/\"a\""), None, "\"a\"", None));
+        assert!(is_not_modified(Some("*"), None, "\"a\"", None));
+        assert!(!is_not_modified(Some("\"b\""), None, "\"a\"", None));
+    }
+
+    #[test]
+    fn if_none_match_takes_precedence() {
+        let date = "Sun, 06 Nov 1994 08:49:37 GMT";
+        assert!(!is_not_modified(Some("\"b\""), Some(date), "\"a\"", Some(0)));
+    }
+
+    #[test]
+    fn if_modified_since() {
+        let date = "Sun, 06 Nov 1994 08:49:37 GMT";
+        assert!(is_not_modified(None, Some(date), "\"a\"", Some(784111777)));
+        assert!(is_not_modified(None, Some(date), "\"a\"", Some(0)));
+        assert!(!is_not_modified(None, Some(date), "\"a\"", Some(784111778)));
+        assert!(!is_not_modified(None, Some(date), "\"a\"", None));
+        assert!(!is_not_modified(None, Some("yesterday"), "\"a\"", Some(0)));
+    }
+
+    #[test]
+    fn cache_control_patterns() {
+        let policies = [
+            ("assets/**".to_string(), "max-age=31536000, immutable".to_string()),
+            ("*.html".to_string(), "no-store".to_string()),
+        ];
+        assert_eq!(cache_control_for("assets/img/logo.png", &policies), "max-age=31536000, immutable");
+        assert_eq!(cache_control_for("index.html", &policies), "no-store");
+        assert_eq!(cache_control_for("ui/menu.html", &policies), DEFAULT_CACHE_CONTROL);
+        assert_eq!(cache_control_for("app.js", &[]), DEFAULT_CACHE_CONTROL);
+    }
+}
@@ -0,0 +1,57 @@
+use godot::classes::{EditorPlugin, IEditorPlugin, ProjectSettings};
+use godot::global::PropertyHint;
+use godot::prelude::*;
+
+use crate::mime::MIME_TYPES_SETTING;
+use crate::typescript::{generate_declarations, TypeScriptNames};
+use crate::write_text_file;
+
+const MENU_ITEM: &str = "Generate godot_wry.d.ts";
+const DECLARATIONS_PATH: &str = "res://godot_wry.d.ts";
+
+#[derive(GodotClass)]
+#[class(tool, init, editor_plugin, base=EditorPlugin)]
+struct GodotWryEditorPlugin {
+    base: Base<EditorPlugin>,
+}
+
+#[godot_api]
+impl IEditorPlugin for GodotWryEditorPlugin {
+    fn enter_tree(&mut self) {
+        let callable = Callable::from_object_method(&self.to_gd(), "generate_typescript_declarations");
+        self.base_mut().add_tool_menu_item(MENU_ITEM, &callable);
+        add_project_settings();
+    }
+
+    fn exit_tree(&mut self) {
+        self.base_mut().remove_tool_menu_item(MENU_ITEM);
+    }
+}
+
+#[godot_api]
+impl GodotWryEditorPlugin {
+    // functions and channels are only known at runtime, use
+    // WebView.save_typescript_declarations() to include them
+    #[func]
+    fn generate_typescript_declarations(&self) {
+        let declarations = generate_declarations(&TypeScriptNames::default());
+        match write_text_file(DECLARATIONS_PATH, &declarations) {
+            Ok(()) => godot_print!("[Godot WRY] TypeScript declarations saved to {}", DECLARATIONS_PATH),
+            Err(error) => godot_error!("[Godot WRY] Could not save {}: {:?}", DECLARATIONS_PATH, error),
+        }
+    }
+}
+
+// Makes the settings show up in Project Settings, with their default values.
+fn add_project_settings() {
+    let mut project_settings = ProjectSettings::singleton();
+    if !project_settings.has_setting(MIME_TYPES_SETTING) {
+        project_settings.set_setting(MIME_TYPES_SETTING, &Dictionary::new().to_variant());
+    }
+    project_settings.set_initial_value(MIME_TYPES_SETTING, &Dictionary::new().to_variant());
+    project_settings.add_property_info(&dict! {
+        "name": MIME_TYPES_SETTING,
+        "type": VariantType::DICTIONARY.ord(),
+        "hint": PropertyHint::NONE.ord(),
+    });
+}
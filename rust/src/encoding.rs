@@ -0,0 +1,96 @@
+// Content negotiation for precompressed files served by the `res` protocol.
+
+use std::io::{self, Read};
+
+// Encodings with the extension of their precompressed siblings, in order of preference.
+pub const PRECOMPRESSED: [(&str, &str); 2] = [("br", ".br"), ("gzip", ".gz")];
+
+// Whether `Accept-Encoding` allows the given encoding. An explicit entry
+// overrides `*`, and `q=0` marks an encoding as not acceptable.
+pub fn accepts_encoding(accept_encoding: &str, encoding: &str) -> bool {
+    let mut wildcard = false;
+    for entry in accept_encoding.split(',') {
+        let mut params = entry.split(';').map(str::trim);
+        let name = params.next().unwrap_or_default();
+        let quality = params
+            .find_map(|param| param.strip_prefix("q=").or_else(|| param.strip_prefix("Q=")))
+            .map(|q| q.trim().parse::<f32>().unwrap_or(0.0))
+            .unwrap_or(1.0);
+
+        if name.eq_ignore_ascii_case(encoding) {
+            return quality > 0.0;
+        }
+        if name == "*" {
+            wildcard = quality > 0.0;
+        }
+    }
+    wildcard
+}
+
+// Decodes a precompressed file for webviews that didn't accept its encoding.
+pub fn decode(encoding: &str, data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut decoded = Vec::new();
+    match encoding {
+        "br" => brotli_decompressor::Decompressor::new(data, 4096).read_to_end(&mut decoded)?,
+        "gzip" => flate2::read::GzDecoder::new(data).read_to_end(&mut decoded)?,
+        _ => return Err(io::Error::new(io::ErrorKind::Unsupported, format!("unsupported encoding {}", encoding))),
+    };
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listed_encodings() {
+        assert!(accepts_encoding("gzip, deflate, br", "br"));
+        assert!(accepts_encoding("gzip, deflate, br", "gzip"));
+        assert!(accepts_encoding("GZIP", "gzip"));
+        assert!(!accepts_encoding("gzip, deflate", "br"));
+        assert!(!accepts_encoding("", "gzip"));
+    }
+
+    #[test]
+    fn quality_values() {
+        assert!(accepts_encoding("br;q=0.5, gzip;q=1.0", "br"));
+        assert!(!accepts_encoding("br;q=0, gzip", "br"));
+        assert!(!accepts_encoding("br; q=0.000", "br"));
+        assert!(!accepts_encoding("br;q=abc", "br"));
+    }
+
+    #[test]
+    fn wildcard() {
+        assert!(accepts_encoding("*", "br"));
+        assert!(!accepts_encoding("*;q=0", "br"));
+        assert!(!accepts_encoding("*, br;q=0", "br"));
+        assert!(accepts_encoding("*;q=0, br", "br"));
+    }
+
+    #[test]
+    fn decodes_brotli() {
+        // `console.log('hello');` compressed with quality 11
+        let compressed = [
+            11, 10, 128, 99, 111, 110, 115, 111, 108, 101, 46, 108, 111, 103, 40, 39, 104, 101, 108, 108, 111, 39,
+            41, 59, 3,
+        ];
+        assert_eq!(decode("br", &compressed).unwrap(), b"console.log('hello');");
+    }
+
+    #[test]
+    fn decodes_gzip() {
+        use std::io::Write;
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b"console.log('hello');").unwrap();
+        let compressed = encoder.finish().unwrap();
+        assert_eq!(decode("gzip", &compressed).unwrap(), b"console.log('hello');");
+    }
+
+    #[test]
+    fn rejects_corrupt_data() {
+        assert!(decode("gzip", b"not gzip").is_err());
+        assert!(decode("br", &[0xff; 16]).is_err());
+        assert!(decode("zstd", b"").is_err());
+    }
+}
@@ -0,0 +1,71 @@
+// Minimal glob matching for origins and paths:
+// `*` matches anything but `/`, `**` matches anything, `?` matches one character but `/`.
+// A leading `**/` also matches zero directories, so `**/*.js` matches `app.js`.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    matches(pattern.as_bytes(), text.as_bytes())
+}
+
+fn matches(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', b'/', rest @ ..] => {
+            matches(rest, text) || (0..=text.len()).any(|i| matches(&pattern[2..], &text[i..]))
+        },
+        [b'*', b'*', rest @ ..] => (0..=text.len()).any(|i| matches(rest, &text[i..])),
+        [b'*', rest @ ..] => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != b'/')
+            .any(|i| matches(rest, &text[i..])),
+        [b'?', rest @ ..] => text.first().is_some_and(|&c| c != b'/') && matches(rest, &text[1..]),
+        [c, rest @ ..] => text.first() == Some(c) && matches(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literal() {
+        assert!(glob_match("res://ui", "res://ui"));
+        assert!(!glob_match("res://ui", "res://uix"));
+        assert!(!glob_match("res://uix", "res://ui"));
+    }
+
+    #[test]
+    fn star_stops_at_slash() {
+        assert!(glob_match("*.js", "app.js"));
+        assert!(!glob_match("*.js", "lib/app.js"));
+        assert!(glob_match("https://*.example.com", "https://app.example.com"));
+        assert!(!glob_match("https://*.example.com", "https://evil.com/.example.com"));
+    }
+
+    #[test]
+    fn star_matches_any_host_suffix() {
+        // patterns like `http://res.*` accept every host starting with `res.`
+        assert!(glob_match("http://res.*", "http://res.evil.com"));
+        assert!(!glob_match("http://res.*", "https://res.evil.com"));
+        assert!(!glob_match("res://*", "https://res.evil.com"));
+    }
+
+    #[test]
+    fn double_star_crosses_folders() {
+        assert!(glob_match("assets/**", "assets/img/logo.png"));
+        assert!(glob_match("**/*.js", "app.js"));
+        assert!(glob_match("**/*.js", "lib/vendor/app.js"));
+        assert!(!glob_match("**/*.js", "lib/app.css"));
+    }
+
+    #[test]
+    fn question_mark() {
+        assert!(glob_match("v?.js", "v1.js"));
+        assert!(!glob_match("v?.js", "v10.js"));
+        assert!(!glob_match("a?b", "a/b"));
+    }
+
+    #[test]
+    fn empty() {
+        assert!(glob_match("", ""));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("", "a"));
+    }
+}
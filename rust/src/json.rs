@@ -0,0 +1,153 @@
+use godot::prelude::*;
+use serde_json::{json, Map, Number, Value};
+
+// Godot types without a JSON counterpart are converted to plain objects,
+// see the "Type conversion" table in docs/reference/webview.md.
+pub fn variant_to_json(variant: &Variant) -> Value {
+    match variant.get_type() {
+        VariantType::NIL => Value::Null,
+        VariantType::BOOL => Value::Bool(variant.to::<bool>()),
+        VariantType::INT => Value::from(variant.to::<i64>()),
+        VariantType::FLOAT => number(variant.to::<f64>()),
+        VariantType::STRING => Value::String(variant.to::<GString>().to_string()),
+        VariantType::STRING_NAME => Value::String(variant.to::<StringName>().to_string()),
+        VariantType::NODE_PATH => Value::String(variant.to::<NodePath>().to_string()),
+        VariantType::VECTOR2 => vector2(variant.to::<Vector2>()),
+        VariantType::VECTOR2I => {
+            let v = variant.to::<Vector2i>();
+            json!({ "x": v.x, "y": v.y })
+        },
+        VariantType::VECTOR3 => vector3(variant.to::<Vector3>()),
+        VariantType::VECTOR3I => {
+            let v = variant.to::<Vector3i>();
+            json!({ "x": v.x, "y": v.y, "z": v.z })
+        },
+        VariantType::VECTOR4 => {
+            let v = variant.to::<Vector4>();
+            json!({ "x": number(v.x as f64), "y": number(v.y as f64), "z": number(v.z as f64), "w": number(v.w as f64) })
+        },
+        VariantType::VECTOR4I => {
+            let v = variant.to::<Vector4i>();
+            json!({ "x": v.x, "y": v.y, "z": v.z, "w": v.w })
+        },
+        VariantType::QUATERNION => {
+            let q = variant.to::<Quaternion>();
+            json!({ "x": number(q.x as f64), "y": number(q.y as f64), "z": number(q.z as f64), "w": number(q.w as f64) })
+        },
+        VariantType::COLOR => color(variant.to::<Color>()),
+        VariantType::RECT2 => {
+            let rect = variant.to::<Rect2>();
+            json!({ "position": vector2(rect.position), "size": vector2(rect.size) })
+        },
+        VariantType::RECT2I => {
+            let rect = variant.to::<Rect2i>();
+            json!({
+                "position": { "x": rect.position.x, "y": rect.position.y },
+                "size": { "x": rect.size.x, "y": rect.size.y },
+            })
+        },
+        VariantType::AABB => {
+            let aabb = variant.to::<Aabb>();
+            json!({ "position": vector3(aabb.position), "size": vector3(aabb.size) })
+        },
+        VariantType::PLANE => {
+            let plane = variant.to::<Plane>();
+            json!({ "normal": vector3(plane.normal), "d": number(plane.d as f64) })
+        },
+        VariantType::BASIS => basis(variant.to::<Basis>()),
+        VariantType::TRANSFORM2D => {
+            let transform = variant.to::<Transform2D>();
+            json!({ "x": vector2(transform.a), "y": vector2(transform.b), "origin": vector2(transform.origin) })
+        },
+        VariantType::TRANSFORM3D => {
+            let transform = variant.to::<Transform3D>();
+            json!({ "basis": basis(transform.basis), "origin": vector3(transform.origin) })
+        },
+        VariantType::PACKED_BYTE_ARRAY => Value::Array(
+            variant.to::<PackedByteArray>().as_slice().iter().map(|b| Value::from(*b)).collect()
+        ),
+        VariantType::PACKED_INT32_ARRAY => Value::Array(
+            variant.to::<PackedInt32Array>().as_slice().iter().map(|i| Value::from(*i)).collect()
+        ),
+        VariantType::PACKED_INT64_ARRAY => Value::Array(
+            variant.to::<PackedInt64Array>().as_slice().iter().map(|i| Value::from(*i)).collect()
+        ),
+        VariantType::PACKED_FLOAT32_ARRAY => Value::Array(
+            variant.to::<PackedFloat32Array>().as_slice().iter().map(|f| number(*f as f64)).collect()
+        ),
+        VariantType::PACKED_FLOAT64_ARRAY => Value::Array(
+            variant.to::<PackedFloat64Array>().as_slice().iter().map(|f| number(*f)).collect()
+        ),
+        VariantType::PACKED_STRING_ARRAY => Value::Array(
+            variant.to::<PackedStringArray>().as_slice().iter().map(|s| Value::String(s.to_string())).collect()
+        ),
+        VariantType::PACKED_VECTOR2_ARRAY => Value::Array(
+            variant.to::<PackedVector2Array>().as_slice().iter().map(|v| vector2(*v)).collect()
+        ),
+        VariantType::PACKED_VECTOR3_ARRAY => Value::Array(
+            variant.to::<PackedVector3Array>().as_slice().iter().map(|v| vector3(*v)).collect()
+        ),
+        VariantType::PACKED_COLOR_ARRAY => Value::Array(
+            variant.to::<PackedColorArray>().as_slice().iter().map(|c| color(*c)).collect()
+        ),
+        VariantType::OBJECT => match variant.try_to::<Gd<Object>>() {
+            Ok(object) => Value::String(object.to_string()),
+            Err(_) => Value::Null,
+        },
+        VariantType::ARRAY => match variant.try_to::<VariantArray>() {
+            Ok(array) => Value::Array(array.iter_shared().map(|item| variant_to_json(&item)).collect()),
+            Err(_) => Value::String(variant.to_string()),
+        },
+        VariantType::DICTIONARY => {
+            let mut map = Map::new();
+            for (key, value) in variant.to::<Dictionary>().iter_shared() {
+                map.insert(key.to_string(), variant_to_json(&value));
+            }
+            Value::Object(map)
+        },
+        _ => Value::String(variant.to_string()),
+    }
+}
+
+fn number(value: f64) -> Value {
+    // NaN and infinity are not representable in JSON
+    Number::from_f64(value).map(Value::Number).unwrap_or(Value::Null)
+}
+
+fn vector2(v: Vector2) -> Value {
+    json!({ "x": number(v.x as f64), "y": number(v.y as f64) })
+}
+
+fn vector3(v: Vector3) -> Value {
+    json!({ "x": number(v.x as f64), "y": number(v.y as f64), "z": number(v.z as f64) })
+}
+
+fn color(c: Color) -> Value {
+    json!({ "r": number(c.r as f64), "g": number(c.g as f64), "b": number(c.b as f64), "a": number(c.a as f64) })
+}
+
+fn basis(basis: Basis) -> Value {
+    json!({ "x": vector3(basis.col_a()), "y": vector3(basis.col_b()), "z": vector3(basis.col_c()) })
+}
+
+pub fn json_to_variant(value: &Value) -> Variant {
+    match value {
+        Value::Null => Variant::nil(),
+        Value::Bool(b) => b.to_variant(),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => i.to_variant(),
+            None => n.as_f64().unwrap_or_default().to_variant(),
+        },
+        Value::String(s) => s.to_variant(),
+        Value::Array(items) => items
+            .iter()
+            .map(json_to_variant)
+            .collect::<VariantArray>()
+            .to_variant(),
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, value)| (key.as_str(), json_to_variant(value)))
+            .collect::<Dictionary>()
+            .to_variant(),
+    }
+}
@@ -0,0 +1,166 @@
+use godot::prelude::*;
+use serde_json::{json, Value};
+
+// Error codes defined by the JSON-RPC 2.0 specification,
+// see https://www.jsonrpc.org/specification#error_object
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+
+#[derive(GodotConvert, Var, Export, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[godot(via = i64)]
+pub enum IpcProtocol {
+    #[default]
+    Default = 0,
+    JsonRpc = 1,
+}
+
+pub struct JsonRpcRequest {
+    // `None` for notifications, which never get a response
+    pub id: Option<Value>,
+    pub method: String,
+    // converted to Variants by the caller, one per argument
+    pub params: Vec<Value>,
+}
+
+// Only objects declaring `"jsonrpc"`, and batches made only of them, are claimed.
+// Anything else is left to the regular IPC handling, so `window.godot`, input
+// forwarding and plain `ipc.postMessage()` payloads keep working.
+pub fn is_json_rpc_message(body: &str) -> bool {
+    let is_request = |message: &Value| message.as_object().is_some_and(|message| message.contains_key("jsonrpc"));
+    match serde_json::from_str::<Value>(body) {
+        Ok(Value::Array(batch)) => !batch.is_empty() && batch.iter().all(is_request),
+        Ok(message) => is_request(&message),
+        Err(_) => false,
+    }
+}
+
+pub fn parse_request(request: &Value) -> Result<JsonRpcRequest, Value> {
+    let Some(object) = request.as_object() else {
+        return Err(error_response(Value::Null, INVALID_REQUEST, "Invalid Request"));
+    };
+
+    let id = match object.get("id") {
+        None => None,
+        Some(id @ (Value::Null | Value::Number(_) | Value::String(_))) => Some(id.clone()),
+        Some(_) => return Err(error_response(Value::Null, INVALID_REQUEST, "Invalid Request")),
+    };
+    let response_id = id.clone().unwrap_or_default();
+
+    if object.get("jsonrpc").and_then(|v| v.as_str()) != Some("2.0") {
+        return Err(error_response(response_id, INVALID_REQUEST, "Invalid Request"));
+    }
+
+    let Some(method) = object.get("method").and_then(|v| v.as_str()) else {
+        return Err(error_response(response_id, INVALID_REQUEST, "Invalid Request"));
+    };
+
+    // named params are handed over as a single Dictionary argument
+    let params = match object.get("params") {
+        None => Vec::new(),
+        Some(Value::Array(params)) => params.clone(),
+        Some(params @ Value::Object(_)) => vec![params.clone()],
+        Some(_) => return Err(error_response(response_id, INVALID_PARAMS, "Invalid params")),
+    };
+
+    Ok(JsonRpcRequest { id, method: method.to_string(), params })
+}
+
+pub fn success_response(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+pub fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_code(response: &Value) -> i64 {
+        response["error"]["code"].as_i64().unwrap()
+    }
+
+    #[test]
+    fn claims_requests_and_batches() {
+        assert!(is_json_rpc_message(r#"{"jsonrpc": "2.0", "method": "ping", "id": 1}"#));
+        assert!(is_json_rpc_message(r#"{"jsonrpc": "1.0", "method": "ping"}"#));
+        assert!(is_json_rpc_message(r#"[{"jsonrpc": "2.0", "method": "a"}, {"jsonrpc": "2.0", "method": "b", "id": 2}]"#));
+    }
+
+    #[test]
+    fn leaves_other_messages_alone() {
+        assert!(!is_json_rpc_message("hello"));
+        assert!(!is_json_rpc_message("{not json"));
+        assert!(!is_json_rpc_message(""));
+        assert!(!is_json_rpc_message("42"));
+        assert!(!is_json_rpc_message(r#""text""#));
+        assert!(!is_json_rpc_message(r#"{"type": "_mouse_move", "x": 1, "y": 2}"#));
+        assert!(!is_json_rpc_message("[1, 2, 3]"));
+        assert!(!is_json_rpc_message("[]"));
+    }
+
+    #[test]
+    fn mixed_batches_are_left_alone() {
+        assert!(!is_json_rpc_message(r#"[{"jsonrpc": "2.0", "method": "a"}, {"type": "score"}]"#));
+        assert!(!is_json_rpc_message(r#"[{"jsonrpc": "2.0", "method": "a"}, 1]"#));
+    }
+
+    #[test]
+    fn request_with_positional_params() {
+        let request = parse_request(&json!({ "jsonrpc": "2.0", "method": "add", "params": [1, 2], "id": 7 })).unwrap();
+        assert_eq!(request.id, Some(json!(7)));
+        assert_eq!(request.method, "add");
+        assert_eq!(request.params, vec![json!(1), json!(2)]);
+    }
+
+    #[test]
+    fn named_params_are_a_single_argument() {
+        let request = parse_request(&json!({ "jsonrpc": "2.0", "method": "save", "params": { "slot": 1 }, "id": "a" })).unwrap();
+        assert_eq!(request.params, vec![json!({ "slot": 1 })]);
+    }
+
+    #[test]
+    fn notifications_have_no_id() {
+        let request = parse_request(&json!({ "jsonrpc": "2.0", "method": "log" })).unwrap();
+        assert_eq!(request.id, None);
+        assert!(request.params.is_empty());
+
+        // a null id is still a request, answered with a null id
+        let request = parse_request(&json!({ "jsonrpc": "2.0", "method": "log", "id": null })).unwrap();
+        assert_eq!(request.id, Some(Value::Null));
+    }
+
+    #[test]
+    fn invalid_id_types() {
+        for id in [json!(true), json!([1]), json!({ "a": 1 })] {
+            let response = parse_request(&json!({ "jsonrpc": "2.0", "method": "a", "id": id })).err().unwrap();
+            assert_eq!(error_code(&response), INVALID_REQUEST);
+            assert_eq!(response["id"], Value::Null);
+        }
+    }
+
+    #[test]
+    fn invalid_requests() {
+        let response = parse_request(&json!({ "method": "a", "id": 1 })).err().unwrap();
+        assert_eq!(error_code(&response), INVALID_REQUEST);
+        assert_eq!(response["id"], json!(1));
+
+        let response = parse_request(&json!({ "jsonrpc": "2.0", "method": 5, "id": 1 })).err().unwrap();
+        assert_eq!(error_code(&response), INVALID_REQUEST);
+
+        let response = parse_request(&json!(1)).err().unwrap();
+        assert_eq!(error_code(&response), INVALID_REQUEST);
+        assert_eq!(response["id"], Value::Null);
+    }
+
+    #[test]
+    fn invalid_params() {
+        let response = parse_request(&json!({ "jsonrpc": "2.0", "method": "a", "params": 3, "id": 1 })).err().unwrap();
+        assert_eq!(error_code(&response), INVALID_PARAMS);
+        assert_eq!(response["id"], json!(1));
+    }
+}
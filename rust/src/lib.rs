@@ -1,24 +1,53 @@
 #[macro_use]
 mod macros;
+mod bridge;
+mod cache;
+mod editor_plugin;
+mod encoding;
+mod glob;
 mod godot_window;
+mod json;
+mod json_rpc;
+mod mime;
+mod origins;
+mod paths;
+mod promise;
 mod protocols;
+mod range;
+mod schemes;
+mod state;
+mod typescript;
 
 use godot::global::MouseButtonMask;
 use godot::init::*;
 use godot::prelude::*;
-use godot::classes::{Control, DisplayServer, IControl, InputEvent, InputEventMouseButton, InputEventMouseMotion, InputEventKey, ProjectSettings, Viewport};
-use godot::global::{Key, MouseButton};
+use godot::classes::file_access::ModeFlags;
+use godot::classes::control::ControlNotification;
+use godot::classes::{ClassDB, Control, Crypto, DisplayServer, FileAccess, IControl, InputEvent, InputEventMouseButton, InputEventMouseMotion, InputEventKey, ProjectSettings, Time, Viewport};
+use godot::global::{Error, Key, MouseButton};
 use lazy_static::lazy_static;
 use serde_json;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::path::PathBuf;
 use wry::{WebViewBuilder, WebContext, Rect, WebViewAttributes, PageLoadEvent};
 use wry::dpi::{PhysicalPosition, PhysicalSize};
-use wry::http::Request;
+use wry::http::header::{HeaderName, HeaderValue};
+use wry::http::{Method, Request};
 
+use crate::bridge::{BRIDGE_SCRIPT, CONSOLE_SCRIPT};
 use crate::godot_window::GodotWindow;
-use crate::protocols::get_res_response;
+use crate::json::{json_to_variant, variant_to_json};
+use crate::json_rpc::{error_response, is_json_rpc_message, parse_request, success_response, IpcProtocol};
+use crate::mime::MIME_TYPES_SETTING;
+use crate::origins::{is_origin_allowed, origin_of, DEFAULT_ALLOWED_ORIGINS};
+use crate::paths::normalize_path;
+use crate::promise::{eval_error, parse_eval_result, WebViewPromise, EVAL_ERROR_KEY};
+use crate::protocols::{allowed_origin, get_bytes_response, method_not_allowed, protocol_error, protocol_request_args, protocol_response, spawn_res_workers, ProtocolRequest, ResOptions, VirtualFile, BYTES_URL};
+use crate::schemes::{is_reserved_scheme, is_valid_scheme};
+use crate::state::{get_path, set_path};
+use crate::typescript::{generate_declarations, TypeScriptNames};
 
 #[cfg(target_os = "windows")]
 use {
@@ -36,6 +65,33 @@ extern "system" {}
 
 struct GodotWRY;
 
+struct PendingScript {
+    script: String,
+    expires_at: Option<u64>,
+    // the `call_js` promise waiting on this script, if any
+    call_id: Option<i64>,
+}
+
+enum RpcError {
+    NotFound(String),
+    Failed(String),
+}
+
+impl RpcError {
+    fn code(&self) -> i64 {
+        match self {
+            RpcError::NotFound(_) => json_rpc::METHOD_NOT_FOUND,
+            RpcError::Failed(_) => json_rpc::INTERNAL_ERROR,
+        }
+    }
+
+    fn into_message(self) -> String {
+        match self {
+            RpcError::NotFound(message) | RpcError::Failed(message) => message,
+        }
+    }
+}
+
 #[gdextension]
 unsafe impl ExtensionLibrary for GodotWRY {}
 
@@ -44,11 +100,38 @@ unsafe impl ExtensionLibrary for GodotWRY {}
 struct WebView {
     base: Base<Control>,
     webview: Option<wry::WebView>,
+    // set from `ready()` until the deferred `create_webview` runs
+    webview_pending: bool,
+    // calls made while `webview_pending`, applied once the webview exists
+    pending_webview_calls: Vec<Box<dyn FnOnce(&wry::WebView)>>,
     window_id: i32,
     previous_global_position: Vector2,
     previous_viewport_size: Vector2i,
     previous_window_position: Vector2i,
     previous_content_scale_factor: f32,
+    functions: HashMap<String, Callable>,
+    subscriptions: HashMap<String, Vec<Callable>>,
+    emitted_channels: HashSet<String>,
+    bridged_signals: HashMap<(InstanceId, String), Vec<(String, Callable)>>,
+    pending_promises: HashMap<i64, Gd<WebViewPromise>>,
+    next_promise_id: i64,
+    eval_results: Arc<Mutex<Vec<(i64, String)>>>,
+    page_ready: bool,
+    pending_scripts: VecDeque<PendingScript>,
+    outgoing_bytes: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+    // tokens of `outgoing_bytes` already announced to the current page
+    announced_bytes: Vec<String>,
+    // `call_js` promises whose call was sent to the current page
+    invoked_calls: Vec<i64>,
+    state: serde_json::Value,
+    protocols: HashMap<String, Callable>,
+    protocol_requests: Arc<Mutex<Vec<ProtocolRequest>>>,
+    res_write_handler: Option<Callable>,
+    // shared with the `res` workers, which answer preflights
+    res_writable: Arc<AtomicBool>,
+    mime_types: Arc<Mutex<HashMap<String, String>>>,
+    virtual_files: Arc<Mutex<HashMap<String, VirtualFile>>>,
+    rebuild_pending: bool,
     #[export]
     full_window_size: bool,
     #[export]
@@ -79,6 +162,30 @@ struct WebView {
     forward_input_events: bool,
     #[export]
     autoplay: bool,
+    #[export]
+    forward_console: bool,
+    #[export]
+    print_console: bool,
+    #[export]
+    rpc_target: NodePath,
+    #[export]
+    pending_ttl: f64,
+    #[export]
+    ipc_allowed_origins: PackedStringArray,
+    #[export]
+    ipc_protocol: IpcProtocol,
+    #[export]
+    cache_control: Dictionary,
+    #[export]
+    spa_mounts: PackedStringArray,
+    #[export]
+    web_root: GString,
+    #[export]
+    denied_extensions: PackedStringArray,
+    #[export]
+    response_headers: Dictionary,
+    #[export]
+    path_headers: Dictionary,
 }
 
 #[godot_api]
@@ -87,11 +194,33 @@ impl IControl for WebView {
         Self {
             base,
             webview: None,
+            webview_pending: false,
+            pending_webview_calls: Vec::new(),
             window_id: 0,
             previous_global_position: Vector2::default(),
             previous_viewport_size: Vector2i::default(),
             previous_window_position: Vector2i::default(),
             previous_content_scale_factor: 1.0,
+            functions: HashMap::new(),
+            subscriptions: HashMap::new(),
+            emitted_channels: HashSet::new(),
+            bridged_signals: HashMap::new(),
+            pending_promises: HashMap::new(),
+            next_promise_id: 0,
+            eval_results: Arc::new(Mutex::new(Vec::new())),
+            page_ready: false,
+            pending_scripts: VecDeque::new(),
+            outgoing_bytes: Arc::new(Mutex::new(HashMap::new())),
+            announced_bytes: Vec::new(),
+            invoked_calls: Vec::new(),
+            state: serde_json::json!({}),
+            protocols: HashMap::new(),
+            protocol_requests: Arc::new(Mutex::new(Vec::new())),
+            res_write_handler: None,
+            res_writable: Arc::new(AtomicBool::new(false)),
+            mime_types: Arc::new(Mutex::new(HashMap::new())),
+            virtual_files: Arc::new(Mutex::new(HashMap::new())),
+            rebuild_pending: false,
             full_window_size: true,
             url: "https://github.com/doceazedo/godot_wry".into(),
             html: "".into(),
@@ -107,11 +236,32 @@ impl IControl for WebView {
             focused_when_created: true,
             forward_input_events: true,
             autoplay: false,
+            forward_console: true,
+            print_console: false,
+            rpc_target: NodePath::default(),
+            pending_ttl: 0.0,
+            ipc_allowed_origins: DEFAULT_ALLOWED_ORIGINS
+                .into_iter()
+                .map(GString::from)
+                .collect(),
+            ipc_protocol: IpcProtocol::Default,
+            cache_control: Dictionary::new(),
+            spa_mounts: PackedStringArray::new(),
+            web_root: "res://".into(),
+            denied_extensions: ["gd", "cs", "tscn", "scn", "tres", "res", "cfg", "import", "remap", "godot", "gdextension", "uid"]
+                .into_iter()
+                .map(GString::from)
+                .collect(),
+            response_headers: dict! { "X-Content-Type-Options": "nosniff" },
+            path_headers: Dictionary::new(),
         }
     }
 
     fn ready(&mut self) {
-        self.create_webview();
+        // parents are ready after their children, so building at the end of the frame
+        // lets their `_ready()` register protocols without rebuilding the webview
+        self.webview_pending = true;
+        self.base_mut().call_deferred("create_webview", &[]);
     }
 
     fn enter_tree(&mut self) {
@@ -126,7 +276,23 @@ impl IControl for WebView {
     }
 
     fn process(&mut self, _delta: f64) {
+        if self.rebuild_pending {
+            self.rebuild_pending = false;
+            self.webview.take();
+            self.reject_invoked_calls("WebView was rebuilt before the call returned");
+            self.build_webview();
+        }
+
         self.update_webview();
+        self.settle_eval_results();
+        self.respond_protocol_requests();
+    }
+
+    fn on_notification(&mut self, what: ControlNotification) {
+        // connections made by bridge_signal live on the source objects, which may outlive this WebView
+        if what == ControlNotification::PREDELETE {
+            self.unbridge_all_signals();
+        }
     }
 
     fn input(&mut self, event: Gd<InputEvent>) {
@@ -151,9 +317,33 @@ impl IControl for WebView {
 
 #[godot_api]
 impl WebView {
+    #[constant]
+    const IPC_PROTOCOL_DEFAULT: i64 = IpcProtocol::Default as i64;
+
+    #[constant]
+    const IPC_PROTOCOL_JSON_RPC: i64 = IpcProtocol::JsonRpc as i64;
+
     #[signal]
     fn ipc_message(message: GString);
 
+    #[signal]
+    fn ipc_message_parsed(data: Variant);
+
+    #[signal]
+    fn ipc_rejected(origin: GString, body: GString);
+
+    #[signal]
+    fn event_received(channel: GString, payload: Variant);
+
+    #[signal]
+    fn bytes_received(channel: GString, data: PackedByteArray);
+
+    #[signal]
+    fn console_message(level: GString, message: GString, source: GString, line: i64);
+
+    #[signal]
+    fn state_changed(path: GString, value: Variant);
+
     #[signal]
     fn page_load_started(message: GString);
 
@@ -213,6 +403,7 @@ impl WebView {
         self.window_id = window_id;
 
         let window = GodotWindow::new(window_id);
+        self.page_ready = false;
 
         // remove WS_CLIPCHILDREN from the window style
         // otherwise, transparent on windows won't work
@@ -275,9 +466,27 @@ impl WebView {
         })
             .with_ipc_handler({
                 let base = Arc::clone(&base);
+                let allowed_origins: Vec<String> = self.ipc_allowed_origins.as_slice()
+                    .iter()
+                    .map(|origin| origin.to_string().to_lowercase())
+                    .collect();
+                let ipc_protocol = self.ipc_protocol;
                 move |req: Request<String>| {
                     let mut base = base.lock().unwrap();
                     let body = req.body().as_str();
+
+                    // any page or iframe can post messages, including synthetic input events
+                    let origin = origin_of(&req.uri().to_string());
+                    if !is_origin_allowed(&origin, &allowed_origins) {
+                        debug_print!("[Godot WRY] IPC message rejected from origin {}", origin);
+                        base.call_deferred("emit_signal", &["ipc_rejected".to_variant(), origin.to_variant(), body.to_variant()]);
+                        return;
+                    }
+
+                    if ipc_protocol == IpcProtocol::JsonRpc && is_json_rpc_message(body) {
+                        base.call_deferred("_on_json_rpc_message", &[body.to_variant()]);
+                        return;
+                    }
                     
                     if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(body) {
                         if let Some(event_type) = json_value.get("type").and_then(|t| t.as_str()) {
@@ -417,6 +626,11 @@ impl WebView {
                                     }
                                     return;
                                 },
+
+                                "_rpc_call" | "_event" | "_console" | "_state_set" | "_js_result" => {
+                                    base.call_deferred("_on_bridge_message", &[body.to_variant()]);
+                                    return;
+                                },
                                 
                                 _ => {}
                             }
@@ -425,6 +639,11 @@ impl WebView {
                     
                     // if we get here, this is a regular IPC message
                     base.call_deferred("emit_signal", &["ipc_message".to_variant(), body.to_variant()]); 
+
+                    let data = serde_json::from_str::<serde_json::Value>(body)
+                        .map(|json_value| json_to_variant(&json_value))
+                        .unwrap_or_else(|_| body.to_variant());
+                    base.call_deferred("emit_signal", &["ipc_message_parsed".to_variant(), data]);
                 }
             })
             .with_on_page_load_handler({
@@ -433,14 +652,72 @@ impl WebView {
                     let mut base = base.lock().unwrap();
 
                     match event {
-                        PageLoadEvent::Started => base.call_deferred("emit_signal", &["page_load_started".to_variant(), url.to_variant()]),
-                        PageLoadEvent::Finished => base.call_deferred("emit_signal", &["page_load_finished".to_variant(), url.to_variant()]),
+                        PageLoadEvent::Started => base.call_deferred("_on_page_load_started", &[url.to_variant()]),
+                        PageLoadEvent::Finished => base.call_deferred("_on_page_load_finished", &[url.to_variant()]),
                     };
                 }
             })
-            .with_custom_protocol(
-                "res".into(), move |_webview_id, request| get_res_response(request),
-            );
+            .with_asynchronous_custom_protocol("res".into(), {
+                let options = Arc::new(ResOptions {
+                    cache_policies: self.cache_control.iter_shared()
+                        .map(|(pattern, value)| (pattern.to_string(), value.to_string()))
+                        .collect(),
+                    spa_mounts: self.spa_mounts.as_slice()
+                        .iter()
+                        .map(|mount| mount.to_string().trim_start_matches("res://").trim_matches('/').to_string())
+                        .collect(),
+                    web_root: self.web_root.to_string().trim_start_matches("res://").trim_matches('/').to_string(),
+                    denied_extensions: self.denied_extensions.as_slice()
+                        .iter()
+                        .map(|extension| extension.to_string().trim_start_matches('.').to_string())
+                        .collect(),
+                    headers: to_header_list(&self.response_headers),
+                    path_headers: self.path_headers.iter_shared()
+                        .filter_map(|(pattern, headers)| match headers.try_to::<Dictionary>() {
+                            Ok(headers) => Some((pattern.to_string(), to_header_list(&headers))),
+                            Err(_) => {
+                                godot_warn!("[Godot WRY] path_headers[\"{}\"] must be a Dictionary of headers", pattern);
+                                None
+                            },
+                        })
+                        .collect(),
+                    mime_types: Arc::clone(&self.mime_types),
+                    project_mime_types: project_mime_types(),
+                    virtual_files: Arc::clone(&self.virtual_files),
+                    allowed_origins: self.ipc_allowed_origins.as_slice()
+                        .iter()
+                        .map(|origin| origin.to_string().to_lowercase())
+                        .collect(),
+                    writable: Arc::clone(&self.res_writable),
+                });
+                let protocol_requests = Arc::clone(&self.protocol_requests);
+                let res_workers = spawn_res_workers(options);
+                move |_webview_id, request, responder| {
+                    // writes are answered by GDScript on the main thread, see `set_res_write_handler`
+                    if request.method() == Method::POST || request.method() == Method::PUT {
+                        protocol_requests.lock().unwrap().push(ProtocolRequest { scheme: "res".into(), request, responder });
+                        return;
+                    }
+
+                    let _ = res_workers.send((request, responder));
+                }
+            })
+            .with_custom_protocol("gdbytes".into(), {
+                let base = Arc::clone(&base);
+                let outgoing_bytes = Arc::clone(&self.outgoing_bytes);
+                let allowed_origins: Vec<String> = self.ipc_allowed_origins.as_slice()
+                    .iter()
+                    .map(|origin| origin.to_string().to_lowercase())
+                    .collect();
+                move |_webview_id, request| get_bytes_response(request, &outgoing_bytes, &allowed_origins, |channel, data| {
+                    let mut base = base.lock().unwrap();
+                    let data = PackedByteArray::from(data.as_slice());
+                    base.call_deferred("emit_signal", &["bytes_received".to_variant(), channel.to_variant(), data.to_variant()]);
+                })
+            })
+            .with_initialization_script(BRIDGE_SCRIPT)
+            .with_initialization_script(&format!("window.godot._bytesUrl = {};", serde_json::json!(BYTES_URL)))
+            .with_initialization_script(&self.functions_script());
 
         let webview_builder = if self.forward_input_events {
             webview_builder.with_initialization_script(r#"
@@ -520,11 +797,32 @@ impl WebView {
             webview_builder
         };
 
+        let mut webview_builder = webview_builder;
+        for scheme in self.protocols.keys() {
+            let scheme = scheme.clone();
+            let protocol_requests = Arc::clone(&self.protocol_requests);
+            webview_builder = webview_builder.with_asynchronous_custom_protocol(scheme.clone(), move |_webview_id, request, responder| {
+                protocol_requests.lock().unwrap().push(ProtocolRequest { scheme: scheme.clone(), request, responder });
+            });
+        }
+
+        let webview_builder = if self.forward_console {
+            webview_builder.with_initialization_script(CONSOLE_SCRIPT)
+        } else {
+            webview_builder
+        };
+
         if !self.url.is_empty() && !self.html.is_empty() {
             godot_error!("[Godot WRY] You have entered both a URL and HTML code. You may only enter one at a time.")
         }
 
-        let webview = webview_builder.build_as_child(&window).unwrap();
+        let webview = match webview_builder.build_as_child(&window) {
+            Ok(webview) => webview,
+            Err(e) => {
+                godot_error!("[Godot WRY] Could not create the webview: {e}");
+                return;
+            },
+        };
         self.webview.replace(webview);
 
         self.resize()
@@ -532,9 +830,19 @@ impl WebView {
 
     #[func]
     fn create_webview(&mut self) {
+        self.webview_pending = false;
+        if self.webview.is_some() || !self.base().is_inside_tree() {
+            self.pending_webview_calls.clear();
+            return;
+        }
+
         self.build_webview();
-        if self.webview.is_none() {
+        let calls = std::mem::take(&mut self.pending_webview_calls);
+        let Some(webview) = &self.webview else {
             return;
+        };
+        for call in calls {
+            call(webview);
         }
 
         let mut viewport = self.base().get_tree().expect("Could not get tree").get_root().expect("Could not get viewport");
@@ -575,10 +883,434 @@ impl WebView {
     }
 
     #[func]
-    fn post_message(&self, message: GString) {
+    fn post_message(&mut self, message: Variant) {
+        self.post_message_with_ttl(message, self.pending_ttl);
+    }
+
+    #[func]
+    fn post_message_with_ttl(&mut self, message: Variant, ttl: f64) {
+        let data = serde_json::json!({ "detail": variant_to_json(&message) });
+        let script = format!("document.dispatchEvent(new CustomEvent('message', {}))", data);
+        self.send_script(script, ttl);
+    }
+
+    #[func]
+    fn flush_pending(&mut self) {
+        let Some(webview) = &self.webview else {
+            return;
+        };
+
+        let now = Time::singleton().get_ticks_msec();
+        for pending in self.pending_scripts.drain(..) {
+            if pending.expires_at.is_none_or(|expires_at| expires_at > now) {
+                let _ = webview.evaluate_script(&pending.script);
+                self.invoked_calls.extend(pending.call_id);
+            }
+        }
+
+        // whatever send_bytes queued has now been announced to this page
+        self.announced_bytes = self.outgoing_bytes.lock().unwrap().keys().cloned().collect();
+    }
+
+    #[func]
+    fn get_pending_count(&self) -> i64 {
+        self.pending_scripts.len() as i64
+    }
+
+    // evaluates the script right away if the page is ready, otherwise queues it
+    // until the next page_load_finished. a ttl (in seconds) drops it from the
+    // queue once expired, zero or less keeps it until it is sent.
+    fn send_script(&mut self, script: String, ttl: f64) {
+        self.send_call_script(script, ttl, None);
+    }
+
+    // like send_script, tracking which page `call_js` promise `call_id` was sent to
+    fn send_call_script(&mut self, script: String, ttl: f64, call_id: Option<i64>) {
+        if self.page_ready {
+            if let Some(webview) = &self.webview {
+                let _ = webview.evaluate_script(&script);
+                self.invoked_calls.extend(call_id);
+                return;
+            }
+        }
+
+        let expires_at = if ttl > 0.0 {
+            Some(Time::singleton().get_ticks_msec() + (ttl * 1000.0) as u64)
+        } else {
+            None
+        };
+        self.pending_scripts.push_back(PendingScript { script, expires_at, call_id });
+    }
+
+    #[func]
+    fn _on_bridge_message(&mut self, body: GString) {
+        let Ok(message) = serde_json::from_str::<serde_json::Value>(&body.to_string()) else {
+            return;
+        };
+
+        match message.get("type").and_then(|t| t.as_str()) {
+            Some("_rpc_call") => {
+                let id = message.get("id").and_then(|v| v.as_i64()).unwrap_or(0);
+                let method = message.get("method").and_then(|v| v.as_str()).unwrap_or_default();
+                let args: Vec<Variant> = message.get("args")
+                    .and_then(|v| v.as_array())
+                    .map(|args| args.iter().map(json_to_variant).collect())
+                    .unwrap_or_default();
+
+                let result = self.call_rpc_method(method, &args).map_err(RpcError::into_message);
+                self.settle_rpc_call(id, result);
+            },
+            Some("_event") => {
+                let channel = message.get("channel").and_then(|v| v.as_str()).unwrap_or_default();
+                let payload = json_to_variant(message.get("payload").unwrap_or(&serde_json::Value::Null));
+                self.dispatch_event(channel, payload);
+            },
+            Some("_console") => {
+                let level = message.get("level").and_then(|v| v.as_str()).unwrap_or("log");
+                let text = message.get("message").and_then(|v| v.as_str()).unwrap_or_default();
+                let source = message.get("source").and_then(|v| v.as_str()).unwrap_or_default();
+                let line = message.get("line").and_then(|v| v.as_i64()).unwrap_or(0);
+
+                if self.print_console {
+                    let location = if source.is_empty() { String::new() } else { format!(" ({}:{})", source, line) };
+                    match level {
+                        "warn" => godot_warn!("[Godot WRY] console.{}: {}{}", level, text, location),
+                        "error" => godot_error!("[Godot WRY] console.{}: {}{}", level, text, location),
+                        _ => godot_print!("[Godot WRY] console.{}: {}{}", level, text, location),
+                    }
+                }
+
+                self.base_mut().emit_signal("console_message", &[
+                    level.to_variant(),
+                    text.to_variant(),
+                    source.to_variant(),
+                    line.to_variant(),
+                ]);
+            },
+            Some("_state_set") => {
+                let path = message.get("path").and_then(|v| v.as_str()).unwrap_or_default();
+                let value = message.get("value").cloned().unwrap_or_default();
+                let variant = json_to_variant(&value);
+
+                set_path(&mut self.state, path, value);
+                self.base_mut().emit_signal("state_changed", &[path.to_variant(), variant]);
+            },
+            Some("_js_result") => {
+                let id = message.get("id").and_then(|v| v.as_i64()).unwrap_or(0);
+                let result = if message.get("ok").and_then(|v| v.as_bool()) == Some(true) {
+                    Ok(json_to_variant(message.get("value").unwrap_or(&serde_json::Value::Null)))
+                } else {
+                    Err(message.get("error").and_then(|e| e.as_str()).unwrap_or("Unknown error").to_string())
+                };
+                self.invoked_calls.retain(|&call| call != id);
+                self.settle_promises(vec![(id, result)]);
+            },
+            _ => {}
+        }
+    }
+
+    #[func]
+    fn _on_json_rpc_message(&mut self, body: GString) {
+        let response = match serde_json::from_str::<serde_json::Value>(&body.to_string()) {
+            Err(_) => Some(error_response(serde_json::Value::Null, json_rpc::PARSE_ERROR, "Parse error")),
+            Ok(serde_json::Value::Array(batch)) if batch.is_empty() => {
+                Some(error_response(serde_json::Value::Null, json_rpc::INVALID_REQUEST, "Invalid Request"))
+            },
+            Ok(serde_json::Value::Array(batch)) => {
+                let responses: Vec<serde_json::Value> = batch.iter()
+                    .filter_map(|request| self.handle_json_rpc_request(request))
+                    .collect();
+                // a batch made only of notifications gets no response at all
+                if responses.is_empty() { None } else { Some(serde_json::Value::Array(responses)) }
+            },
+            Ok(request) => self.handle_json_rpc_request(&request),
+        };
+
+        if let (Some(response), Some(webview)) = (response, &self.webview) {
+            let _ = webview.evaluate_script(&format!("window.godot._receiveJsonRpc({})", response));
+        }
+    }
+
+    #[func]
+    fn _on_page_load_started(&mut self, url: GString) {
+        self.page_ready = false;
+
+        // bytes the previous page never took; the ones still queued are for the next page
+        let mut outgoing_bytes = self.outgoing_bytes.lock().unwrap();
+        for token in self.announced_bytes.drain(..) {
+            outgoing_bytes.remove(&token);
+        }
+        drop(outgoing_bytes);
+
+        // the previous page can't answer calls anymore, queued ones go to the next page
+        self.reject_invoked_calls("Page navigated before the call returned");
+
+        self.base_mut().emit_signal("page_load_started", &[url.to_variant()]);
+    }
+
+    #[func]
+    fn _on_page_load_finished(&mut self, url: GString) {
+        self.page_ready = true;
+        // functions registered after the webview was built are not part of its
+        // initialization scripts, so they have to be injected again on every page
+        self.sync_functions();
+        self.sync_state();
+        self.flush_pending();
+        self.base_mut().emit_signal("page_load_finished", &[url.to_variant()]);
+    }
+
+    #[func]
+    fn register_function(&mut self, name: GString, callable: Callable) {
+        self.functions.insert(name.to_string(), callable);
+        self.sync_functions();
+    }
+
+    #[func]
+    fn unregister_function(&mut self, name: GString) {
+        if self.functions.remove(&name.to_string()).is_some() {
+            self.sync_functions();
+        }
+    }
+
+    fn functions_script(&self) -> String {
+        let mut names: Vec<&String> = self.functions.keys().collect();
+        names.sort();
+        format!("window.godot._setFunctions({})", serde_json::json!(names))
+    }
+
+    fn sync_functions(&self) {
         if let Some(webview) = &self.webview {
-            let data = serde_json::json!({ "detail": String::from(message) });
-            let script = format!("document.dispatchEvent(new CustomEvent('message', {}))", data);
+            let _ = webview.evaluate_script(&self.functions_script());
+        }
+    }
+
+    #[func]
+    fn emit_event(&mut self, channel: GString, payload: Variant) {
+        self.emitted_channels.insert(channel.to_string());
+        let script = format!(
+            "window.godot._dispatch({}, {})",
+            serde_json::Value::String(channel.to_string()),
+            variant_to_json(&payload)
+        );
+        self.send_script(script, self.pending_ttl);
+    }
+
+    #[func]
+    fn send_bytes(&mut self, channel: GString, data: PackedByteArray) {
+        // any page can request gdbytes URLs, so sequential ids would be guessable
+        let token: String = Crypto::new_gd().generate_random_bytes(16)
+            .as_slice()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        self.outgoing_bytes.lock().unwrap().insert(token.clone(), data.as_slice().to_vec());
+        if self.page_ready {
+            self.announced_bytes.push(token.clone());
+        }
+
+        // the page fetches the bytes itself, so they never go through evaluate_script.
+        // queued without a ttl: if the script was dropped, the bytes would never be taken
+        let script = format!(
+            "window.godot._receiveBytes({}, {})",
+            serde_json::Value::String(channel.to_string()),
+            serde_json::Value::String(token)
+        );
+        self.send_script(script, 0.0);
+    }
+
+    #[func]
+    fn bridge_signal(&mut self, object: Gd<Object>, signal_name: StringName, js_event_name: GString) {
+        if !object.has_signal(&signal_name) {
+            godot_error!("[Godot WRY] Cannot bridge signal \"{}\": not found on {}", signal_name, object);
+            return;
+        }
+
+        let mut webview = self.to_gd();
+        let event_name = serde_json::Value::String(js_event_name.to_string());
+        let callable = Callable::from_local_fn("bridge_signal", move |args: &[&Variant]| {
+            if webview.is_instance_valid() {
+                let detail = serde_json::Value::Array(args.iter().map(|arg| variant_to_json(arg)).collect());
+                let script = format!("document.dispatchEvent(new CustomEvent({}, {{ detail: {} }}))", event_name, detail);
+                let ttl = webview.bind().pending_ttl;
+                webview.bind_mut().send_script(script, ttl);
+            }
+            Ok(Variant::nil())
+        });
+
+        let mut object = object;
+        object.connect(&signal_name, &callable);
+        self.bridged_signals
+            .entry((object.instance_id(), signal_name.to_string()))
+            .or_default()
+            .push((js_event_name.to_string(), callable));
+    }
+
+    #[func]
+    fn unbridge_signal(&mut self, object: Gd<Object>, signal_name: StringName) {
+        let Some(callables) = self.bridged_signals.remove(&(object.instance_id(), signal_name.to_string())) else {
+            return;
+        };
+
+        let mut object = object;
+        for (_, callable) in callables {
+            if object.is_connected(&signal_name, &callable) {
+                object.disconnect(&signal_name, &callable);
+            }
+        }
+    }
+
+    fn unbridge_all_signals(&mut self) {
+        for ((instance_id, signal_name), callables) in self.bridged_signals.drain() {
+            let Ok(mut object) = Gd::<Object>::try_from_instance_id(instance_id) else {
+                continue;
+            };
+            let signal_name = StringName::from(signal_name.as_str());
+            for (_, callable) in callables {
+                if object.is_connected(&signal_name, &callable) {
+                    object.disconnect(&signal_name, &callable);
+                }
+            }
+        }
+    }
+
+    #[func]
+    fn generate_typescript_declarations(&self) -> GString {
+        let names = TypeScriptNames {
+            functions: self.functions.keys().cloned().collect(),
+            incoming_channels: self.emitted_channels.iter().cloned().collect(),
+            outgoing_channels: self.subscriptions.keys().cloned().collect(),
+            dom_events: self.bridged_signals
+                .values()
+                .flatten()
+                .map(|(event_name, _)| event_name.clone())
+                .collect(),
+        };
+        generate_declarations(&names).into()
+    }
+
+    #[func]
+    fn save_typescript_declarations(&self, path: GString) -> Error {
+        let declarations = self.generate_typescript_declarations();
+        match write_text_file(&path.to_string(), &declarations.to_string()) {
+            Ok(()) => Error::OK,
+            Err(error) => error,
+        }
+    }
+
+    #[func]
+    fn set_state(&mut self, path: GString, value: Variant) {
+        let path = path.to_string();
+        let json = variant_to_json(&value);
+        set_path(&mut self.state, &path, json.clone());
+
+        // while the page is loading there's nothing to patch, it gets the whole state once loaded
+        if self.page_ready {
+            if let Some(webview) = &self.webview {
+                let script = format!("window.godot.state._apply({}, {})", serde_json::Value::String(path.clone()), json);
+                let _ = webview.evaluate_script(&script);
+            }
+        }
+
+        self.base_mut().emit_signal("state_changed", &[path.to_variant(), value]);
+    }
+
+    #[func]
+    fn get_state(&self, path: GString) -> Variant {
+        get_path(&self.state, &path.to_string())
+            .map(json_to_variant)
+            .unwrap_or_default()
+    }
+
+    fn sync_state(&self) {
+        if let Some(webview) = &self.webview {
+            let _ = webview.evaluate_script(&format!("window.godot.state._apply('', {})", self.state));
+        }
+    }
+
+    #[func]
+    fn subscribe(&mut self, channel: GString, callable: Callable) {
+        self.subscriptions.entry(channel.to_string()).or_default().push(callable);
+    }
+
+    #[func]
+    fn unsubscribe(&mut self, channel: GString, callable: Callable) {
+        if let Some(callables) = self.subscriptions.get_mut(&channel.to_string()) {
+            callables.retain(|c| *c != callable);
+            if callables.is_empty() {
+                self.subscriptions.remove(&channel.to_string());
+            }
+        }
+    }
+
+    fn dispatch_event(&mut self, channel: &str, payload: Variant) {
+        let callables = self.subscriptions.get(channel).cloned().unwrap_or_default();
+
+        let mut base = self.base_mut();
+        for callable in callables.iter().filter(|c| c.is_valid()) {
+            callable.callv(&varray![payload.clone()]);
+        }
+        base.emit_signal("event_received", &[channel.to_variant(), payload]);
+    }
+
+    fn call_rpc_method(&mut self, method: &str, args: &[Variant]) -> Result<Variant, RpcError> {
+        if let Some(callable) = self.functions.get(method).cloned() {
+            if !callable.is_valid() {
+                return Err(RpcError::Failed(format!("Function \"{}\" is no longer valid", method)));
+            }
+
+            let args = args.iter().cloned().collect::<VariantArray>();
+            let _guard = self.base_mut();
+            return Ok(callable.callv(&args));
+        }
+
+        // only methods added by the target's script are exposed, never the ones of
+        // Node/Object/Control (`queue_free`, `set`, `get_node`...) or private ones
+        if self.rpc_target.is_empty() || method.is_empty() || method.starts_with('_') {
+            return Err(RpcError::NotFound(format!("Method \"{}\" not found", method)));
+        }
+
+        let Some(mut target) = self.base().get_node_or_null(&self.rpc_target) else {
+            return Err(RpcError::Failed(format!("RPC target \"{}\" not found", self.rpc_target)));
+        };
+
+        let class = StringName::from(&target.get_class());
+        if !target.has_method(method) || ClassDB::singleton().class_has_method(&class, method) {
+            return Err(RpcError::NotFound(format!("Method \"{}\" not found", method)));
+        }
+
+        // keep the base borrowed while calling out so the method can call back into this WebView
+        let _guard = self.base_mut();
+        Ok(target.call(method, args))
+    }
+
+    fn handle_json_rpc_request(&mut self, request: &serde_json::Value) -> Option<serde_json::Value> {
+        let request = match parse_request(request) {
+            Ok(request) => request,
+            Err(response) => return Some(response),
+        };
+
+        let params: Vec<Variant> = request.params.iter().map(json_to_variant).collect();
+        let result = self.call_rpc_method(&request.method, &params);
+
+        // notifications are dispatched but never answered, not even on errors
+        let id = request.id?;
+        Some(match result {
+            Ok(value) => success_response(id, variant_to_json(&value)),
+            Err(error) => {
+                let code = error.code();
+                error_response(id, code, &error.into_message())
+            },
+        })
+    }
+
+    fn settle_rpc_call(&self, id: i64, result: Result<Variant, String>) {
+        let (ok, value) = match result {
+            Ok(value) => (true, variant_to_json(&value)),
+            Err(error) => (false, serde_json::Value::String(error)),
+        };
+
+        if let Some(webview) = &self.webview {
+            let script = format!("window.godot._settle({}, {}, {})", id, ok, value);
             let _ = webview.evaluate_script(&script);
         }
     }
@@ -629,9 +1361,173 @@ impl WebView {
     }
 
     #[func]
-    fn eval(&self, script: GString) {
-        if let Some(webview) = &self.webview {
-            let _ = webview.evaluate_script(&*String::from(script));
+    fn eval(&mut self, script: GString) {
+        self.send_script(String::from(script), self.pending_ttl);
+    }
+
+    #[func]
+    fn eval_with_result(&mut self, script: GString) -> Gd<WebViewPromise> {
+        let (id, promise) = self.create_promise();
+
+        // exceptions can't cross the native boundary reliably, so they are caught and reported in-band.
+        // the script is inlined rather than passed to `eval()`, which a CSP without 'unsafe-eval' blocks;
+        // a `try` statement completes with the value of its last statement, like the script would
+        let script = format!(
+            "try {{\n{}\n}} catch (e) {{ ({{ {}: String(e) }}) }}",
+            script,
+            EVAL_ERROR_KEY
+        );
+
+        let results = Arc::clone(&self.eval_results);
+        let evaluated = self.webview.as_ref()
+            .map(|webview| webview.evaluate_script_with_callback(&script, move |result| {
+                results.lock().unwrap().push((id, result));
+            }).is_ok())
+            .unwrap_or(false);
+
+        // always settle on a later frame, so the caller has a chance to await it
+        if !evaluated {
+            self.eval_results.lock().unwrap().push((id, eval_error("WebView is not available")));
+        }
+
+        promise
+    }
+
+    #[func]
+    fn call_js(&mut self, function_path: GString, args: VariantArray) -> Gd<WebViewPromise> {
+        let (id, promise) = self.create_promise();
+
+        // the page posts the (awaited) return value back as a `_js_result` message
+        let args = serde_json::Value::Array(args.iter_shared().map(|arg| variant_to_json(&arg)).collect());
+        let script = format!(
+            "window.godot._invoke({}, {}, {})",
+            id,
+            serde_json::Value::String(function_path.to_string()),
+            args
+        );
+        self.send_call_script(script, 0.0, Some(id));
+
+        promise
+    }
+
+    #[func]
+    fn register_protocol(&mut self, scheme: GString, handler: Callable) {
+        let scheme = scheme.to_string().to_lowercase();
+        if !is_valid_scheme(&scheme) {
+            godot_error!("[Godot WRY] Cannot register protocol \"{}\": not a valid URL scheme", scheme);
+            return;
+        }
+        if is_reserved_scheme(&scheme) {
+            godot_error!("[Godot WRY] Cannot register protocol \"{}\": reserved by the webview or Godot WRY", scheme);
+            return;
+        }
+
+        // wry only accepts custom protocols when the webview is built
+        let is_new = self.protocols.insert(scheme, handler).is_none();
+        if is_new && self.webview.is_some() {
+            self.rebuild_pending = true;
+        }
+    }
+
+    #[func]
+    fn set_res_write_handler(&mut self, handler: Callable) {
+        self.res_write_handler = handler.is_valid().then_some(handler);
+        self.res_writable.store(self.res_write_handler.is_some(), Ordering::Relaxed);
+    }
+
+    // Takes effect immediately, an empty `mime` restores the default type.
+    #[func]
+    fn set_mime_type(&mut self, extension: GString, mime: GString) {
+        let extension = extension.to_string().trim_start_matches('.').to_lowercase();
+        let mut mime_types = self.mime_types.lock().unwrap();
+        if mime.is_empty() {
+            mime_types.remove(&extension);
+        } else {
+            mime_types.insert(extension, mime.to_string());
+        }
+    }
+
+    // Served from memory by the `res` protocol, taking precedence over project files.
+    #[func]
+    fn set_virtual_file(&mut self, path: GString, data: PackedByteArray, mime: GString) {
+        let Some(path) = virtual_file_path(&path) else {
+            godot_error!("[Godot WRY] Invalid virtual file path \"{}\"", path);
+            return;
+        };
+        let file = VirtualFile::new(data.to_vec(), mime.to_string());
+        self.virtual_files.lock().unwrap().insert(path, file);
+    }
+
+    #[func]
+    fn remove_virtual_file(&mut self, path: GString) {
+        if let Some(path) = virtual_file_path(&path) {
+            self.virtual_files.lock().unwrap().remove(&path);
+        }
+    }
+
+    fn respond_protocol_requests(&mut self) {
+        let requests = std::mem::take(&mut *self.protocol_requests.lock().unwrap());
+        let allowed_origins: Vec<String> = self.ipc_allowed_origins.as_slice()
+            .iter()
+            .map(|origin| origin.to_string().to_lowercase())
+            .collect();
+        for ProtocolRequest { scheme, request, responder } in requests {
+            let handler = if scheme == "res" {
+                self.res_write_handler.clone()
+            } else {
+                self.protocols.get(&scheme).cloned()
+            };
+
+            let response = match handler {
+                Some(handler) if handler.is_valid() => {
+                    let args = protocol_request_args(&scheme, &request).into_iter().collect::<VariantArray>();
+                    let origin = allowed_origin(&request, &allowed_origins);
+                    let _guard = self.base_mut();
+                    protocol_response(&handler.callv(&args), origin.as_deref())
+                },
+                _ if scheme == "res" => method_not_allowed(),
+                _ => protocol_error(404, &format!("No handler for protocol \"{}\"", scheme)),
+            };
+            responder.respond(response);
+        }
+    }
+
+    fn create_promise(&mut self) -> (i64, Gd<WebViewPromise>) {
+        self.next_promise_id += 1;
+        let promise = WebViewPromise::new_gd();
+        self.pending_promises.insert(self.next_promise_id, promise.clone());
+        (self.next_promise_id, promise)
+    }
+
+    fn settle_eval_results(&mut self) {
+        let results = std::mem::take(&mut *self.eval_results.lock().unwrap());
+        let results = results
+            .into_iter()
+            .map(|(id, raw)| (id, parse_eval_result(&raw)))
+            .collect();
+        self.settle_promises(results);
+    }
+
+    fn reject_invoked_calls(&mut self, reason: &str) {
+        let rejected = self.invoked_calls
+            .drain(..)
+            .map(|id| (id, Err(reason.to_string())))
+            .collect();
+        self.settle_promises(rejected);
+    }
+
+    fn settle_promises(&mut self, results: Vec<(i64, Result<Variant, String>)>) {
+        let settled: Vec<_> = results
+            .into_iter()
+            .filter_map(|(id, result)| self.pending_promises.remove(&id).map(|promise| (promise, result)))
+            .collect();
+        if settled.is_empty() {
+            return;
+        }
+
+        let _guard = self.base_mut();
+        for (mut promise, result) in settled {
+            WebViewPromise::settle(&mut promise, result);
         }
     }
 
@@ -651,22 +1547,39 @@ impl WebView {
         }
     }
 
-    #[func]
-    fn set_visible(&self, visibility: bool) {
+    // Runs `call` on the webview, or once it is created if that is still pending
+    // (it is built at the end of the frame, after the parents' `_ready()`).
+    fn with_webview(&mut self, call: impl FnOnce(&wry::WebView) + 'static) {
         if let Some(webview) = &self.webview {
-            let _ = webview.set_visible(visibility);
+            call(webview);
+        } else if self.webview_pending {
+            self.pending_webview_calls.push(Box::new(call));
         }
     }
 
     #[func]
-    fn load_html(&self, html: GString) {
+    fn set_visible(&mut self, visibility: bool) {
+        self.with_webview(move |webview| {
+            let _ = webview.set_visible(visibility);
+        });
+    }
+
+    #[func]
+    fn load_html(&mut self, html: GString) {
+        // loaded as the initial page instead, so the default one isn't loaded first
+        if self.webview_pending {
+            self.url = GString::new();
+            self.html = html;
+            return;
+        }
+
         if let Some(webview) = &self.webview {
             let _ = webview.load_html(&*String::from(html));
         }
     }
 
     #[func]
-    fn load_url(&self, url: GString) {
+    fn load_url(&mut self, url: GString) {
         let mut url_str = String::from(url);
 
         if let Some(stripped) = url_str.strip_prefix("res://") {
@@ -683,30 +1596,36 @@ impl WebView {
             }
         }
 
+        if self.webview_pending {
+            self.html = GString::new();
+            self.url = url_str.into();
+            return;
+        }
+
         if let Some(webview) = &self.webview {
             let _ = webview.load_url(&url_str);
         }
     }
 
     #[func]
-    fn clear_all_browsing_data(&self) {
-        if let Some(webview) = &self.webview {
+    fn clear_all_browsing_data(&mut self) {
+        self.with_webview(|webview| {
             let _ = webview.clear_all_browsing_data();
-        }
+        });
     }
 
     #[func]
-    fn close_devtools(&self) {
-        if let Some(webview) = &self.webview {
+    fn close_devtools(&mut self) {
+        self.with_webview(|webview| {
             let _ = webview.close_devtools();
-        }
+        });
     }
 
     #[func]
-    fn open_devtools(&self) {
-        if let Some(webview) = &self.webview {
+    fn open_devtools(&mut self) {
+        self.with_webview(|webview| {
             let _ = webview.open_devtools();
-        }
+        });
     }
 
     #[func]
@@ -718,10 +1637,10 @@ impl WebView {
     }
 
     #[func]
-    fn focus(&self) {
-        if let Some(webview) = &self.webview {
+    fn focus(&mut self) {
+        self.with_webview(|webview| {
             let _ = webview.focus();
-        }
+        });
     }
 
     #[func]
@@ -746,10 +1665,56 @@ impl WebView {
     }
 
     #[func]
-    fn zoom(&self, scale_factor: f64) {
-        if let Some(webview) = &self.webview {
+    fn zoom(&mut self, scale_factor: f64) {
+        self.with_webview(move |webview| {
             let _ = webview.zoom(scale_factor);
-        }
+        });
+    }
+}
+
+pub(crate) fn write_text_file(path: &str, contents: &str) -> Result<(), Error> {
+    let Some(mut file) = FileAccess::open(path, ModeFlags::WRITE) else {
+        return Err(FileAccess::get_open_error());
+    };
+    file.store_string(contents);
+    Ok(())
+}
+
+fn to_header_list(headers: &Dictionary) -> Vec<(HeaderName, HeaderValue)> {
+    headers.iter_shared()
+        .filter_map(|(name, value)| {
+            let header = HeaderName::from_bytes(name.to_string().as_bytes())
+                .ok()
+                .zip(HeaderValue::from_str(&value.to_string()).ok());
+            if header.is_none() {
+                godot_warn!("[Godot WRY] Ignoring invalid response header \"{}: {}\"", name, value);
+            }
+            header
+        })
+        .collect()
+}
+
+// Virtual files are keyed like request paths: relative to `res://`, without `.` or `..` segments.
+fn virtual_file_path(path: &GString) -> Option<String> {
+    let path = path.to_string();
+    normalize_path(path.trim_start_matches("res://")).filter(|path| !path.is_empty())
+}
+
+// Extension -> MIME type overrides from the project settings.
+fn project_mime_types() -> HashMap<String, String> {
+    let setting = ProjectSettings::singleton().get_setting(MIME_TYPES_SETTING);
+    if setting.is_nil() {
+        return HashMap::new();
+    }
+
+    match setting.try_to::<Dictionary>() {
+        Ok(mime_types) => mime_types.iter_shared()
+            .map(|(extension, mime)| (extension.to_string().trim_start_matches('.').to_lowercase(), mime.to_string()))
+            .collect(),
+        Err(_) => {
+            godot_warn!("[Godot WRY] {} must be a Dictionary of extension -> MIME type", MIME_TYPES_SETTING);
+            HashMap::new()
+        },
     }
 }
 
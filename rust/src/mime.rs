@@ -0,0 +1,120 @@
+// MIME type helpers for files served by the `res` protocol.
+
+// Project setting with extension -> MIME type overrides, e.g. `{ "map": "application/json" }`.
+pub const MIME_TYPES_SETTING: &str = "godot_wry/protocols/mime_types";
+
+// How many bytes `sniff_mime_type` needs at most.
+pub const SNIFF_LENGTH: usize = 512;
+
+const TEXT_TYPES: [&str; 7] = [
+    "application/javascript",
+    "application/json",
+    "application/ld+json",
+    "application/manifest+json",
+    "application/xhtml+xml",
+    "application/xml",
+    "image/svg+xml",
+];
+
+// Adds `charset=utf-8` to text types without parameters.
+pub fn with_charset(mime_type: &str) -> String {
+    if !mime_type.contains(';') && (mime_type.starts_with("text/") || TEXT_TYPES.contains(&mime_type)) {
+        format!("{}; charset=utf-8", mime_type)
+    } else {
+        mime_type.to_string()
+    }
+}
+
+// Guesses the type of files with unknown extensions from their first bytes.
+pub fn sniff_mime_type(head: &[u8]) -> &'static str {
+    const SIGNATURES: [(&[u8], &str); 14] = [
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"\0asm", "application/wasm"),
+        (b"glTF", "model/gltf-binary"),
+        (b"\xabKTX 20\xbb\r\n\x1a\n", "image/ktx2"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"OggS", "audio/ogg"),
+        (b"fLaC", "audio/flac"),
+        (b"ID3", "audio/mpeg"),
+        (b"wOFF", "font/woff"),
+        (b"wOF2", "font/woff2"),
+    ];
+
+    if let Some((_, mime_type)) = SIGNATURES.iter().find(|(signature, _)| head.starts_with(signature)) {
+        return mime_type;
+    }
+
+    match head {
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => return "image/webp",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => return "audio/wav",
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => return "video/mp4",
+        _ => {},
+    }
+
+    // a multi-byte character may be cut at the end of `head`
+    let text = match std::str::from_utf8(head) {
+        Ok(text) => text,
+        Err(error) if error.error_len().is_none() => std::str::from_utf8(&head[..error.valid_up_to()]).unwrap_or_default(),
+        Err(_) => return "application/octet-stream",
+    };
+    if text.chars().any(|c| c.is_control() && !c.is_whitespace()) {
+        return "application/octet-stream";
+    }
+
+    let start = text.trim_start().trim_start_matches('\u{feff}').to_ascii_lowercase();
+    if start.starts_with("<!doctype html") || start.starts_with("<html") {
+        "text/html"
+    } else if start.starts_with("<svg") {
+        "image/svg+xml"
+    } else if start.starts_with("<?xml") {
+        "application/xml"
+    } else {
+        "text/plain"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_charset_to_text_types() {
+        assert_eq!(with_charset("text/html"), "text/html; charset=utf-8");
+        assert_eq!(with_charset("application/json"), "application/json; charset=utf-8");
+        assert_eq!(with_charset("image/svg+xml"), "image/svg+xml; charset=utf-8");
+    }
+
+    #[test]
+    fn keeps_other_types() {
+        assert_eq!(with_charset("image/png"), "image/png");
+        assert_eq!(with_charset("text/html; charset=iso-8859-1"), "text/html; charset=iso-8859-1");
+    }
+
+    #[test]
+    fn sniffs_binary_formats() {
+        assert_eq!(sniff_mime_type(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), "image/png");
+        assert_eq!(sniff_mime_type(b"\xff\xd8\xff\xe0"), "image/jpeg");
+        assert_eq!(sniff_mime_type(b"RIFF\x24\0\0\0WEBPVP8 "), "image/webp");
+        assert_eq!(sniff_mime_type(b"RIFF\x24\0\0\0WAVEfmt "), "audio/wav");
+        assert_eq!(sniff_mime_type(b"\0\0\0\x20ftypisom"), "video/mp4");
+        assert_eq!(sniff_mime_type(b"\0asm\x01\0\0\0"), "application/wasm");
+        assert_eq!(sniff_mime_type(b"glTF\x02\0\0\0"), "model/gltf-binary");
+        assert_eq!(sniff_mime_type(b"\x01\x02\x03\x04"), "application/octet-stream");
+        assert_eq!(sniff_mime_type(b"\xc3\x28abc"), "application/octet-stream");
+    }
+
+    #[test]
+    fn sniffs_text_formats() {
+        assert_eq!(sniff_mime_type(b"  <!DOCTYPE html><html>"), "text/html");
+        assert_eq!(sniff_mime_type(b"<HTML>"), "text/html");
+        assert_eq!(sniff_mime_type(b"<svg xmlns=\"http://www.w3.org/2000/svg\">"), "image/svg+xml");
+        assert_eq!(sniff_mime_type(b"<?xml version=\"1.0\"?>"), "application/xml");
+        assert_eq!(sniff_mime_type(b"hello\nworld"), "text/plain");
+        assert_eq!(sniff_mime_type(b"caf\xc3"), "text/plain");
+        assert_eq!(sniff_mime_type(b""), "text/plain");
+    }
+}
@@ -0,0 +1,105 @@
+// Origins allowed to talk to Godot over IPC and the `gdbytes` protocol.
+
+use crate::glob::glob_match;
+
+// Only the project's own pages. Custom protocols are only reachable as
+// `http://<name>.<path>` on Windows and Android, where the webview intercepts
+// that host before it reaches the network; elsewhere `http(s)://res.*` would
+// be a real remote site.
+#[cfg(any(target_os = "windows", target_os = "android"))]
+pub const DEFAULT_ALLOWED_ORIGINS: [&str; 1] = ["http://res.*"];
+#[cfg(not(any(target_os = "windows", target_os = "android")))]
+pub const DEFAULT_ALLOWED_ORIGINS: [&str; 1] = ["res://*"];
+
+// `origin` is lowercase `scheme://authority`, a single `*` pattern allows every origin.
+pub fn is_origin_allowed(origin: &str, allowed_origins: &[String]) -> bool {
+    allowed_origins.iter().any(|pattern| pattern == "*" || glob_match(pattern, origin))
+}
+
+// Lowercase `scheme://authority` of a URL. URLs without an authority, like
+// `about:blank` or the `null` origin, are returned as is.
+pub fn origin_of(url: &str) -> String {
+    let url = url.trim();
+    match url.split_once("://") {
+        Some((scheme, rest)) => {
+            let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+            format!("{}://{}", scheme, authority).to_lowercase()
+        },
+        None => url.to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn origin_of_urls() {
+        assert_eq!(origin_of("res://ui/index.html"), "res://ui");
+        assert_eq!(origin_of("http://res.localhost/ui/index.html?x=1"), "http://res.localhost");
+        assert_eq!(origin_of("HTTPS://Example.com:8080#top"), "https://example.com:8080");
+        assert_eq!(origin_of("https://example.com"), "https://example.com");
+        assert_eq!(origin_of("about:blank"), "about:blank");
+        assert_eq!(origin_of("null"), "null");
+    }
+
+    fn origins(patterns: &[&str]) -> Vec<String> {
+        patterns.iter().map(|pattern| pattern.to_string()).collect()
+    }
+
+    #[test]
+    fn res_pages_are_allowed_by_default() {
+        let allowed = origins(&DEFAULT_ALLOWED_ORIGINS);
+        if cfg!(any(target_os = "windows", target_os = "android")) {
+            assert!(is_origin_allowed("http://res.localhost", &allowed));
+        } else {
+            assert!(is_origin_allowed("res://ui", &allowed));
+            assert!(is_origin_allowed("res://localhost", &allowed));
+        }
+    }
+
+    #[test]
+    fn remote_hosts_are_rejected_by_default() {
+        let allowed = origins(&DEFAULT_ALLOWED_ORIGINS);
+        assert!(!is_origin_allowed("https://res.evil.com", &allowed));
+        assert!(!is_origin_allowed("https://example.com", &allowed));
+        assert!(!is_origin_allowed("about:blank", &allowed));
+        assert!(!is_origin_allowed("null", &allowed));
+        if cfg!(not(any(target_os = "windows", target_os = "android"))) {
+            assert!(!is_origin_allowed("http://res.attacker.net", &allowed));
+        }
+    }
+
+    #[test]
+    fn res_scheme_pattern_only_matches_res_scheme() {
+        let allowed = origins(&["res://*"]);
+        assert!(!is_origin_allowed("https://res.evil.com", &allowed));
+        assert!(!is_origin_allowed("http://res.attacker.net", &allowed));
+        assert!(!is_origin_allowed("resx://ui", &allowed));
+        assert!(!is_origin_allowed("https://evil.com/res://ui", &allowed));
+    }
+
+    #[test]
+    fn http_res_pattern_rejects_https_and_lookalikes() {
+        let allowed = origins(&["http://res.*"]);
+        assert!(is_origin_allowed("http://res.localhost", &allowed));
+        assert!(!is_origin_allowed("https://res.evil.com", &allowed));
+        assert!(!is_origin_allowed("http://resevil.com", &allowed));
+        assert!(!is_origin_allowed("http://evil.com/res.x", &allowed));
+    }
+
+    #[test]
+    fn explicit_origins() {
+        let allowed = origins(&["https://example.com", "https://*.example.com"]);
+        assert!(is_origin_allowed("https://example.com", &allowed));
+        assert!(is_origin_allowed("https://app.example.com", &allowed));
+        assert!(!is_origin_allowed("https://example.com.evil.com", &allowed));
+        assert!(!is_origin_allowed("http://example.com", &allowed));
+    }
+
+    #[test]
+    fn wildcard_allows_every_origin() {
+        assert!(is_origin_allowed("https://anything.net", &origins(&["*"])));
+        assert!(!is_origin_allowed("https://anything.net", &[]));
+    }
+}
@@ -0,0 +1,134 @@
+// Request path checks for the `res` protocol. Paths are relative to `res://`,
+// without leading or trailing slashes.
+
+// Percent-decodes the path and resolves `.` and `..` segments. Returns `None`
+// for paths escaping `res://` or containing NUL characters.
+pub fn normalize_path(raw: &str) -> Option<String> {
+    let decoded = percent_decode(raw);
+    if decoded.contains('\0') {
+        return None;
+    }
+
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in decoded.split(['/', '\\']) {
+        match segment {
+            "" | "." => {},
+            ".." => {
+                segments.pop()?;
+            },
+            segment => segments.push(segment),
+        }
+    }
+    Some(segments.join("/"))
+}
+
+// An empty root allows the whole project.
+pub fn is_inside_root(path: &str, root: &str) -> bool {
+    root.is_empty() || path == root || path.strip_prefix(root).is_some_and(|rest| rest.starts_with('/'))
+}
+
+// Hidden files and folders (`.godot/`, `.git/`) are always denied.
+pub fn is_denied(path: &str, denied_extensions: &[String]) -> bool {
+    // `:` is never needed by web files, but Windows reads `player.gd::$DATA`
+    // (alternate data streams) and `C:` (drives) through it
+    if path.split('/').any(|segment| segment.starts_with('.') || segment.contains(':')) {
+        return true;
+    }
+
+    // Windows ignores trailing dots and spaces, `player.gd.` opens `player.gd`
+    let file_name = path.rsplit('/').next().unwrap_or_default().trim_end_matches(['.', ' ']);
+    match file_name.rsplit_once('.') {
+        Some((_, extension)) => denied_extensions.iter().any(|denied| denied.eq_ignore_ascii_case(extension)),
+        None => false,
+    }
+}
+
+pub fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+            if let (Some(hi), Some(lo)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                decoded.push((hi << 4) | lo);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_paths() {
+        assert_eq!(normalize_path("ui/index.html").as_deref(), Some("ui/index.html"));
+        assert_eq!(normalize_path("/ui//./index.html/").as_deref(), Some("ui/index.html"));
+        assert_eq!(normalize_path("ui/css/../index.html").as_deref(), Some("ui/index.html"));
+        assert_eq!(normalize_path("ui\\index.html").as_deref(), Some("ui/index.html"));
+        assert_eq!(normalize_path("").as_deref(), Some(""));
+    }
+
+    #[test]
+    fn rejects_traversal() {
+        assert_eq!(normalize_path("../project.godot"), None);
+        assert_eq!(normalize_path("ui/../../project.godot"), None);
+        assert_eq!(normalize_path("ui/%2e%2e/%2E%2E/project.godot"), None);
+        assert_eq!(normalize_path("ui%2f..%2f..%2fproject.godot"), None);
+        assert_eq!(normalize_path("ui\\..\\..\\project.godot"), None);
+    }
+
+    #[test]
+    fn rejects_nul() {
+        assert_eq!(normalize_path("ui/index.html%00.png"), None);
+    }
+
+    #[test]
+    fn web_root() {
+        assert!(is_inside_root("ui/index.html", "ui"));
+        assert!(is_inside_root("ui", "ui"));
+        assert!(is_inside_root("anything.html", ""));
+        assert!(!is_inside_root("ui2/index.html", "ui"));
+        assert!(!is_inside_root("scripts/player.gd", "ui"));
+    }
+
+    #[test]
+    fn denied_files() {
+        let denied = ["gd".to_string(), "cfg".to_string()];
+        assert!(is_denied("scripts/player.gd", &denied));
+        assert!(is_denied("scripts/player.GD", &denied));
+        assert!(is_denied("export_presets.cfg", &denied));
+        assert!(is_denied(".godot/imported/icon.png", &denied));
+        assert!(is_denied("ui/.env", &denied));
+        assert!(!is_denied("ui/app.js", &denied));
+        assert!(!is_denied("ui/gd", &denied));
+        assert!(!is_denied("ui/player.gd.html", &denied));
+    }
+
+    #[test]
+    fn denied_files_with_windows_aliases() {
+        let denied = ["gd".to_string()];
+        assert!(is_denied("scripts/player.gd.", &denied));
+        assert!(is_denied("scripts/player.gd ", &denied));
+        assert!(is_denied("scripts/player.gd. . ", &denied));
+        assert!(is_denied("scripts/player.gd::$DATA", &denied));
+        assert!(is_denied("scripts/player.gd:stream", &denied));
+        assert!(is_denied("C:/project/scripts/player.js", &denied));
+        assert!(!is_denied("ui/app.js.", &denied));
+    }
+
+    #[test]
+    fn denied_files_after_percent_decoding() {
+        let denied = ["gd".to_string()];
+        let path = normalize_path("scripts/player.gd%20").unwrap();
+        assert!(is_denied(&path, &denied));
+        let path = normalize_path("scripts/player.gd%3A%3A$DATA").unwrap();
+        assert!(is_denied(&path, &denied));
+    }
+}
@@ -0,0 +1,81 @@
+use godot::prelude::*;
+use serde_json::Value;
+
+use crate::json::json_to_variant;
+
+#[derive(GodotClass)]
+#[class(base=RefCounted, init)]
+pub struct WebViewPromise {
+    base: Base<RefCounted>,
+    settled: bool,
+    result: Variant,
+    error: GString,
+}
+
+#[godot_api]
+impl WebViewPromise {
+    #[signal]
+    fn completed(result: Variant);
+
+    #[func]
+    fn is_completed(&self) -> bool {
+        self.settled
+    }
+
+    #[func]
+    fn is_error(&self) -> bool {
+        self.settled && !self.error.is_empty()
+    }
+
+    #[func]
+    fn get_result(&self) -> Variant {
+        self.result.clone()
+    }
+
+    #[func]
+    fn get_error(&self) -> GString {
+        self.error.clone()
+    }
+}
+
+impl WebViewPromise {
+    pub fn settle(promise: &mut Gd<Self>, result: Result<Variant, String>) {
+        let value = {
+            let mut this = promise.bind_mut();
+            this.settled = true;
+            match result {
+                Ok(value) => {
+                    this.result = value.clone();
+                    value
+                },
+                Err(error) => {
+                    this.error = if error.is_empty() { "Error".into() } else { error.into() };
+                    Variant::nil()
+                },
+            }
+        };
+
+        // emitted outside of bind_mut(), awaiting scripts resume right away and may read the promise
+        promise.emit_signal("completed", &[value]);
+    }
+}
+
+// Scripts evaluated by `eval_with_result` complete with `{ [EVAL_ERROR_KEY]: message }` when they throw.
+pub const EVAL_ERROR_KEY: &str = "__godotWryError";
+
+// `raw` is the completion value of the script, which platforms hand back as JSON.
+pub fn parse_eval_result(raw: &str) -> Result<Variant, String> {
+    let value = match raw.trim() {
+        "" | "undefined" => Value::Null,
+        raw => serde_json::from_str::<Value>(raw).unwrap_or_else(|_| Value::String(raw.to_string())),
+    };
+
+    match value.as_object().filter(|object| object.len() == 1).and_then(|object| object.get(EVAL_ERROR_KEY)) {
+        Some(error) => Err(error.as_str().unwrap_or("Unknown error").to_string()),
+        None => Ok(json_to_variant(&value)),
+    }
+}
+
+pub fn eval_error(message: &str) -> String {
+    serde_json::json!({ EVAL_ERROR_KEY: message }).to_string()
+}
@@ -1,32 +1,193 @@
-use godot::builtin::GString;
+use godot::prelude::*;
 use godot::classes::file_access::ModeFlags;
-use godot::classes::FileAccess;
-use http::{Request, Response};
-use http::header::{ACCEPT_RANGES, CONTENT_RANGE, CONTENT_TYPE, RANGE};
+use godot::classes::{FileAccess, OS};
+use http::{Method, Request, Response, Uri};
+use http::header::{HeaderName, HeaderValue, ACCEPT, ACCEPT_ENCODING, ACCEPT_RANGES, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_HEADERS, ALLOW, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED, ORIGIN, RANGE, REFERER, VARY};
 use lazy_static::lazy_static;
 use std::borrow::Cow;
+use std::collections::hash_map::RandomState;
 use std::collections::HashMap;
+use std::hash::{BuildHasher, DefaultHasher, Hash, Hasher};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use wry::RequestAsyncResponder;
+
+use crate::cache::{cache_control_for, format_http_date, is_not_modified};
+use crate::encoding::{accepts_encoding, decode, PRECOMPRESSED};
+use crate::glob::glob_match;
+use crate::mime::{sniff_mime_type, with_charset, SNIFF_LENGTH};
+use crate::origins::{is_origin_allowed, origin_of};
+use crate::paths::{is_denied, is_inside_root, normalize_path, percent_decode};
+use crate::range::{if_range_matches, multipart_byteranges, parse_range, RangeRequest};
+
+// Custom protocols are reachable as `http://<name>.<path>` on Windows and
+// Android, and as `<name>://<path>` everywhere else.
+#[cfg(any(target_os = "windows", target_os = "android"))]
+pub const BYTES_URL: &str = "http://gdbytes.localhost";
+#[cfg(not(any(target_os = "windows", target_os = "android")))]
+pub const BYTES_URL: &str = "gdbytes://localhost";
+
+const READ_CHUNK_SIZE: u64 = 1024 * 1024;
+// Threads serving `res` requests, so at most this many files are buffered at once.
+const RES_WORKERS: usize = 4;
+const MAX_RANGE_LENGTH: u64 = 8 * 1024 * 1024;
+
+// Settings of the `res` protocol, taken from the WebView when it is built.
+#[derive(Default)]
+pub struct ResOptions {
+    // (path pattern, Cache-Control value)
+    pub cache_policies: Vec<(String, String)>,
+    // path prefixes of single-page apps, relative to `res://`
+    pub spa_mounts: Vec<String>,
+    // relative to `res://`, empty to serve the whole project
+    pub web_root: String,
+    pub denied_extensions: Vec<String>,
+    // added to every response
+    pub headers: Vec<(HeaderName, HeaderValue)>,
+    // (path pattern, headers), applied in order after `headers`
+    pub path_headers: Vec<(String, Vec<(HeaderName, HeaderValue)>)>,
+    // extension -> MIME type, from `WebView.set_mime_type` and the project settings
+    pub mime_types: Arc<Mutex<HashMap<String, String>>>,
+    pub project_mime_types: HashMap<String, String>,
+    // normalized path -> file, from `WebView.set_virtual_file`
+    pub virtual_files: Arc<Mutex<HashMap<String, VirtualFile>>>,
+    // lowercase `ipc_allowed_origins` patterns, echoed in preflights
+    pub allowed_origins: Vec<String>,
+    // whether a write handler is set, see `WebView.set_res_write_handler`
+    pub writable: Arc<AtomicBool>,
+}
+
+// A file generated at runtime, served instead of anything at the same path in the project.
+pub struct VirtualFile {
+    pub data: Vec<u8>,
+    // empty to pick the type like for regular files
+    pub mime_type: String,
+    pub etag: String,
+}
+
+impl VirtualFile {
+    pub fn new(data: Vec<u8>, mime_type: String) -> Self {
+        // hashing the content keeps the ETag stable across restarts, so cached copies stay valid
+        let mut hasher = DefaultHasher::new();
+        data.hash(&mut hasher);
+        mime_type.hash(&mut hasher);
+        let etag = format!("\"{:016x}\"", hasher.finish());
+        Self { data, mime_type, etag }
+    }
+}
+
+pub type ResJob = (Request<Vec<u8>>, RequestAsyncResponder);
+
+// Starts the threads answering `res` requests. The queue itself is unbounded, as
+// blocking would stall the thread wry calls us from, but it only holds requests:
+// files are read by the workers. They stop once the returned sender is dropped
+// along with the webview.
+pub fn spawn_res_workers(options: Arc<ResOptions>) -> Sender<ResJob> {
+    let (sender, receiver) = mpsc::channel::<ResJob>();
+    let receiver = Arc::new(Mutex::new(receiver));
+
+    for i in 0..RES_WORKERS {
+        let receiver = Arc::clone(&receiver);
+        let options = Arc::clone(&options);
+        std::thread::Builder::new()
+            .name(format!("godot_wry res {}", i))
+            .spawn(move || loop {
+                // the lock is released before the response is built
+                let job = receiver.lock().unwrap().recv();
+                let Ok((request, responder)) = job else {
+                    break;
+                };
+                responder.respond(get_res_response(request, &options));
+            })
+            .expect("Failed to spawn res protocol worker");
+    }
+    sender
+}
+
+// Runs on a worker thread (see `spawn_res_workers`), so reading large files doesn't stall the game.
+fn get_res_response(request: Request<Vec<u8>>, options: &ResOptions) -> Response<Cow<'static, [u8]>> {
+    let path = normalize_path(&request_path(request.uri()));
+    let mut response = get_file_response(request, options);
+
+    // configured headers win over the ones set while serving the file
+    let path_headers = options.path_headers
+        .iter()
+        .filter(|(pattern, _)| path.as_deref().is_some_and(|path| glob_match(pattern, path)))
+        .flat_map(|(_, headers)| headers);
+    for (name, value) in options.headers.iter().chain(path_headers) {
+        response.headers_mut().insert(name.clone(), value.clone());
+    }
+    response
+}
+
+fn request_path(uri: &Uri) -> String {
+    format!("{}{}", uri.host().unwrap_or_default(), uri.path())
+}
+
+fn get_file_response(request: Request<Vec<u8>>, options: &ResOptions) -> Response<Cow<'static, [u8]>> {
+    // POST and PUT never get here when a write handler is set, see `WebView.set_res_write_handler`
+    let method = request.method().clone();
+    if method == Method::OPTIONS {
+        return preflight_response(&request, options);
+    }
+    if method != Method::GET && method != Method::HEAD {
+        return method_not_allowed();
+    }
 
-pub fn get_res_response(request: Request<Vec<u8>>) -> Response<Cow<'static, [u8]>> {
     let root = PathBuf::from("res://");
     let uri = request.uri().clone();
-    let path = format!(
-        "{}{}",
-        uri.host().unwrap_or_default(),
-        uri.path()
-    );
+    let raw_path = request_path(&uri);
+
+    // virtual files are added on purpose, so they skip the web root and denied extensions
+    if let Some(path) = normalize_path(&raw_path) {
+        if let Some(response) = virtual_file_response(&request, &path, options) {
+            return response;
+        }
+    }
+
+    // `..` segments (even percent-encoded) could otherwise reach scripts and project settings
+    let path = match normalize_path(&raw_path) {
+        Some(path) if is_inside_root(&path, &options.web_root) && !is_denied(&path, &options.denied_extensions) => path,
+        _ => {
+            debug_print!("[WRY Protocol] 403 Forbidden: {}", raw_path);
+            return http::Response::builder()
+                .header(CONTENT_TYPE, "text/plain")
+                .status(403)
+                .body(Cow::from(format!("Access to {} is forbidden", raw_path).into_bytes()))
+                .expect("Failed to build 403 response");
+        },
+    };
     let mut full_path = root.join(&path);
 
     debug_print!("[WRY Protocol] Request: {} | scheme={} host={} path={}", uri, uri.scheme_str().unwrap_or("?"), uri.host().unwrap_or("?"), uri.path());
     debug_print!("[WRY Protocol] Resolved full_path: {:?}", full_path);
 
-    let mut full_path_str = GString::from(full_path.to_str().unwrap_or_default());
-    if !FileAccess::file_exists(&full_path_str) {
+    let accept_encoding = request.headers().get(ACCEPT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    let full_path_str = GString::from(full_path.to_str().unwrap_or_default());
+    let mut variant = find_variant(&full_path_str, accept_encoding);
+
+    // client-side routes of single-page apps don't exist as files, navigating
+    // to (or reloading) one of them serves the app's index.html as is
+    let accepts_html = request.headers().get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/html"));
+    if let Some(mount) = spa_mount(&path, &options.spa_mounts).filter(|_| variant.is_none() && accepts_html) {
+        let index_path = root.join(mount).join("index.html");
+        let index_path_str = GString::from(index_path.to_str().unwrap_or_default());
+        debug_print!("[WRY Protocol] File not found: {:?}, serving single-page app {:?}", full_path, index_path);
+        variant = find_variant(&index_path_str, accept_encoding);
+        full_path = index_path;
+    } else if variant.is_none() {
         debug_print!("[WRY Protocol] File not found: {:?}, trying index.html fallback", full_path);
         let index_path = full_path.join("index.html");
         let index_path_str = GString::from(index_path.to_str().unwrap_or_default());
-        if FileAccess::file_exists(&index_path_str) {
+        let index_variant = find_variant(&index_path_str, accept_encoding);
+        if index_variant.is_some() {
             if !uri.path().ends_with('/') {
                 let redirect_url = format!(
                     "http://res.{}{}/",
@@ -47,11 +208,11 @@ pub fn get_res_response(request: Request<Vec<u8>>) -> Response<Cow<'static, [u8]
                 debug_print!("[WRY Protocol] Trailing slash present, serving index.html directly: {:?}", index_path);
             }
             full_path = index_path;
-            full_path_str = index_path_str;
+            variant = index_variant;
         }
     }
 
-    if !FileAccess::file_exists(&full_path_str) {
+    let Some((file_path, encoding)) = variant else {
         debug_print!("[WRY Protocol] 404 Not Found: {:?}", full_path);
         return http::Response::builder()
             .header(CONTENT_TYPE, "text/plain")
@@ -62,87 +223,492 @@ pub fn get_res_response(request: Request<Vec<u8>>) -> Response<Cow<'static, [u8]
                     .to_vec(),
             ))
             .expect("Failed to build 404 response");
-    }
+    };
 
+    // precompressed files keep the MIME type of the original file
     let extension = full_path
             .extension()
             .unwrap_or_default()
             .to_str()
             .unwrap_or_default();
 
-    let content_type = MIME_TYPES
-            .get(extension)
-            .unwrap_or(&"application/octet-stream");
-
-    return FileAccess::open(&full_path_str, ModeFlags::READ)
-        .map(|mut file| {
-            let file_size: u64 = file.get_length().try_into().expect("failed to get file size");
-
-            // The client might request a file with Range,
-            // even if we set Accept-Ranges to none, Safari does this while loading media types.
-            // So, we MUST implement the Content-Range logic to serve the file correctly.
-            let mut content_range: Option<(u64, u64)> = None;
-            if let Some(range) = request.headers().get(RANGE) {
-                let range_str = range.to_str().expect("failed to parse Range header");
-
-                // the range header might be in the format "bytes=start-end", "bytes=start-", or "bytes=-end"
-                let parts: Vec<&str> = range_str[6..].split('-').collect();
-                let start = parts[0].parse::<u64>().unwrap_or(0);
-                let end = parts[1].parse::<u64>().unwrap_or(file_size - 1);
-                content_range = Some((start, end));
-            }
+    let Some(mut file) = FileAccess::open(&file_path, ModeFlags::READ) else {
+        return http::Response::builder()
+            .header(CONTENT_TYPE, "text/plain")
+            .status(500)
+            .body(Cow::from(
+                format!("Failed to open at {:?}", full_path)
+                    .as_bytes()
+                    .to_vec(),
+            ))
+            .expect("Failed to build 500 response");
+    };
+    let stored_size = file.get_length();
 
-            return if let Some((start, end)) = content_range {
-                if start >= file_size {
+    // Only a compressed sibling exists but the webview didn't ask for its encoding:
+    // not every webview advertises (or decodes) encodings for custom schemes.
+    let (mut body, file_size, encoding) = match encoding {
+        Some(encoding) if !accepts_encoding(accept_encoding, encoding) => {
+            let compressed = read_file_range(&mut file, 0, stored_size);
+            match decode(encoding, &compressed) {
+                Ok(decoded) => {
+                    let decoded_size = decoded.len() as u64;
+                    (FileBody::Decoded(decoded), decoded_size, None)
+                },
+                Err(error) => {
                     return http::Response::builder()
-                        .header(CONTENT_TYPE, *content_type)
-                        .header(ACCEPT_RANGES, "bytes")
-                        .header(CONTENT_RANGE, format!("bytes */{}", file_size))
-                        .status(416) // Range Not Satisfiable
-                        .body(Cow::from(Vec::new()))
-                        .expect("Failed to build 416 response");
-                }
-
-                let end = if end == 0 || end >= file_size {
-                    file_size - 1
-                } else {
-                    end
-                };
-
-                let content_size = (end - start + 1) as i64;
-                file.seek(start);
-                let content = file.get_buffer(content_size).as_slice().to_vec();
-
-                http::Response::builder()
-                    .header(CONTENT_TYPE, *content_type)
-                    .header(ACCEPT_RANGES, "bytes")
-                    .header(CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, file_size))
-                    .status(206)
-                    .body(Cow::from(content))
-                    .expect("Failed to build 206 response")
-            } else {
-                let content_size = file_size as i64;
-                let content = file.get_buffer(content_size).as_slice().to_vec();
-                http::Response::builder()
-                    .header(CONTENT_TYPE, *content_type)
-                    .status(200)
-                    .body(Cow::from(content))
-                    .expect("Failed to build 200 response")
+                        .header(CONTENT_TYPE, "text/plain")
+                        .status(500)
+                        .body(Cow::from(format!("Failed to decode {:?}: {}", file_path, error).into_bytes()))
+                        .expect("Failed to build 500 response");
+                },
             }
-        }).unwrap_or_else(|| {
-            http::Response::builder()
-                .header(CONTENT_TYPE, "text/plain")
-                .status(500)
-                .body(Cow::from(
-                    format!("Failed to open at {:?}", full_path)
-                        .as_bytes()
-                        .to_vec(),
-                ))
-                .expect("Failed to build 404 response")
-        });
+        },
+        encoding => (FileBody::File(file), stored_size, encoding),
+    };
+
+    // the first bytes of precompressed files say nothing about the original type
+    let mime_type = match mime_type_for(extension, options) {
+        Some(mime_type) => mime_type,
+        None if encoding.is_some() => "application/octet-stream".to_string(),
+        None => sniff_mime_type(&body.read(0, SNIFF_LENGTH as u64)).to_string(),
+    };
+    let content_type = with_charset(&mime_type);
+
+    let modified_time = FileAccess::get_modified_time(&file_path);
+    let modified_at = (modified_time > 0).then_some(modified_time);
+    let last_modified = modified_at.map(format_http_date);
+    let etag = match body {
+        FileBody::File(_) => file_etag(&file_path, stored_size, modified_time),
+        // decoded bytes are a different representation than the stored ones
+        FileBody::Decoded(_) => file_etag(&file_path, stored_size, modified_time).replacen('"', "\"identity-", 1),
+    };
+    let relative_path = full_path.to_str().unwrap_or_default().trim_start_matches("res://").to_string();
+    let cache_control = cache_control_for(&relative_path, &options.cache_policies);
+
+    let header = |name: HeaderName| request.headers().get(name).and_then(|value| value.to_str().ok());
+    let cached_response = || {
+        let builder = http::Response::builder()
+            .header(ETAG, &etag)
+            .header(CACHE_CONTROL, cache_control)
+            .header(VARY, "Accept-Encoding");
+        let builder = match &last_modified {
+            Some(last_modified) => builder.header(LAST_MODIFIED, last_modified),
+            None => builder,
+        };
+        // ranges apply to the encoded bytes, which have their own ETag
+        match encoding {
+            Some(encoding) => builder.header(CONTENT_ENCODING, encoding),
+            None => builder,
+        }
+    };
+
+    if is_not_modified(header(IF_NONE_MATCH), header(IF_MODIFIED_SINCE), &etag, modified_at) {
+        return cached_response()
+            .status(304)
+            .body(Cow::from(Vec::new()))
+            .expect("Failed to build 304 response");
+    }
+
+    // Range only applies to GET, HEAD describes the whole file without reading it
+    if method == Method::HEAD {
+        return cached_response()
+            .header(CONTENT_TYPE, &content_type)
+            .header(ACCEPT_RANGES, "bytes")
+            .header(CONTENT_LENGTH, file_size)
+            .status(200)
+            .body(Cow::from(Vec::new()))
+            .expect("Failed to build HEAD response");
+    }
+
+    // The client might request a file with Range, Safari does this while loading media types.
+    // Serving ranges in bounded chunks also lets media start playing before the whole file is read.
+    // Headers that aren't visible ASCII are ignored like any other invalid Range.
+    let range_is_current = match request.headers().get(IF_RANGE) {
+        Some(if_range) => if_range.to_str().is_ok_and(|if_range| if_range_matches(if_range, Some(&etag), modified_at)),
+        None => true,
+    };
+    let range_request = match header(RANGE) {
+        Some(range) if range_is_current => parse_range(range, file_size, MAX_RANGE_LENGTH),
+        _ => RangeRequest::Full,
+    };
+
+    match range_request {
+        RangeRequest::Full => {
+            let content = body.read(0, file_size);
+            cached_response()
+                .header(CONTENT_TYPE, &content_type)
+                .header(ACCEPT_RANGES, "bytes")
+                .status(200)
+                .body(Cow::from(content))
+                .expect("Failed to build 200 response")
+        },
+        RangeRequest::Partial(ranges) if ranges.len() == 1 => {
+            let range = ranges[0];
+            let content = body.read(range.start, range.length());
+            cached_response()
+                .header(CONTENT_TYPE, &content_type)
+                .header(ACCEPT_RANGES, "bytes")
+                .header(CONTENT_RANGE, format!("bytes {}-{}/{}", range.start, range.end, file_size))
+                .status(206)
+                .body(Cow::from(content))
+                .expect("Failed to build 206 response")
+        },
+        RangeRequest::Partial(ranges) => {
+            let parts: Vec<_> = ranges
+                .into_iter()
+                .map(|range| (range, body.read(range.start, range.length())))
+                .collect();
+            let boundary = format!("godot_wry_{:016x}", RandomState::new().build_hasher().finish());
+            cached_response()
+                .header(CONTENT_TYPE, format!("multipart/byteranges; boundary={}", boundary))
+                .header(ACCEPT_RANGES, "bytes")
+                .status(206)
+                .body(Cow::from(multipart_byteranges(&parts, &content_type, file_size, &boundary)))
+                .expect("Failed to build 206 response")
+        },
+        RangeRequest::Unsatisfiable => http::Response::builder()
+            .header(CONTENT_TYPE, &content_type)
+            .header(ACCEPT_RANGES, "bytes")
+            .header(CONTENT_RANGE, format!("bytes */{}", file_size))
+            .status(416) // Range Not Satisfiable
+            .body(Cow::from(Vec::new()))
+            .expect("Failed to build 416 response"),
+    }
+}
+
+fn virtual_file_response(
+    request: &Request<Vec<u8>>,
+    path: &str,
+    options: &ResOptions,
+) -> Option<Response<Cow<'static, [u8]>>> {
+    let (data, mime_type, etag) = {
+        let virtual_files = options.virtual_files.lock().unwrap();
+        let file = virtual_files.get(path)?;
+        (file.data.clone(), file.mime_type.clone(), file.etag.clone())
+    };
+    debug_print!("[WRY Protocol] Serving virtual file: {}", path);
+
+    let mime_type = if mime_type.is_empty() {
+        let extension = path.rsplit_once('.').map(|(_, extension)| extension).unwrap_or_default();
+        mime_type_for(extension, options)
+            .unwrap_or_else(|| sniff_mime_type(&data[..data.len().min(SNIFF_LENGTH)]).to_string())
+    } else {
+        mime_type
+    };
+
+    let header = |name: HeaderName| request.headers().get(name).and_then(|value| value.to_str().ok());
+    let builder = http::Response::builder()
+        .header(ETAG, &etag)
+        .header(CACHE_CONTROL, cache_control_for(path, &options.cache_policies));
+
+    if is_not_modified(header(IF_NONE_MATCH), header(IF_MODIFIED_SINCE), &etag, None) {
+        return Some(builder.status(304).body(Cow::from(Vec::new())).expect("Failed to build 304 response"));
+    }
+
+    let builder = builder
+        .header(CONTENT_TYPE, with_charset(&mime_type))
+        .header(CONTENT_LENGTH, data.len())
+        .status(200);
+    let body = if request.method() == Method::HEAD { Vec::new() } else { data };
+    Some(builder.body(Cow::from(body)).expect("Failed to build 200 response"))
+}
+
+// `WebView.set_mime_type` overrides the project settings, which override the built-in table.
+fn mime_type_for(extension: &str, options: &ResOptions) -> Option<String> {
+    let extension = extension.to_lowercase();
+    options.mime_types.lock().unwrap().get(&extension)
+        .or_else(|| options.project_mime_types.get(&extension))
+        .cloned()
+        .or_else(|| MIME_TYPES.get(extension.as_str()).map(|mime_type| mime_type.to_string()))
+}
+
+// Only allowed origins pass, unless `response_headers` sets Access-Control-Allow-Origin,
+// which replaces this one like on every other response (see `get_res_response`).
+fn preflight_response(request: &Request<Vec<u8>>, options: &ResOptions) -> Response<Cow<'static, [u8]>> {
+    let methods = if options.writable.load(Ordering::Relaxed) {
+        "GET, HEAD, OPTIONS, POST, PUT"
+    } else {
+        "GET, HEAD, OPTIONS"
+    };
+    let allowed_headers = request.headers().get(ACCESS_CONTROL_REQUEST_HEADERS)
+        .cloned()
+        .unwrap_or(HeaderValue::from_static("*"));
+
+    let mut builder = http::Response::builder().header(ALLOW, methods);
+    if let Some(origin) = allowed_origin(request, &options.allowed_origins) {
+        builder = builder
+            .header(ACCESS_CONTROL_ALLOW_ORIGIN, origin)
+            .header(VARY, "Origin");
+    }
+    builder
+        .header(ACCESS_CONTROL_ALLOW_METHODS, methods)
+        .header(ACCESS_CONTROL_ALLOW_HEADERS, allowed_headers)
+        .header(ACCESS_CONTROL_MAX_AGE, "600")
+        .status(204)
+        .body(Cow::from(Vec::new()))
+        .expect("Failed to build 204 response")
+}
+
+pub fn method_not_allowed() -> Response<Cow<'static, [u8]>> {
+    http::Response::builder()
+        .header(ALLOW, "GET, HEAD, OPTIONS")
+        .header(CONTENT_TYPE, "text/plain")
+        .status(405)
+        .body(Cow::from(b"Method Not Allowed".to_vec()))
+        .expect("Failed to build 405 response")
+}
+
+// The longest mount that contains the path, if any.
+fn spa_mount<'a>(path: &str, mounts: &'a [String]) -> Option<&'a str> {
+    let path = path.trim_matches('/');
+    mounts
+        .iter()
+        .map(|mount| mount.as_str())
+        .filter(|mount| {
+            mount.is_empty()
+                || path == *mount
+                || path.strip_prefix(mount).is_some_and(|rest| rest.starts_with('/'))
+        })
+        .max_by_key(|mount| mount.len())
+}
+
+// Precompressed siblings (`app.js.br`, `app.js.gz`) are preferred when the page
+// accepts them, then the file itself. Siblings the page didn't accept are the
+// last resort and get decoded, so exports can ship them without the original.
+fn find_variant(path: &GString, accept_encoding: &str) -> Option<(GString, Option<&'static str>)> {
+    let variant = |(encoding, extension): &(&'static str, &str)| {
+        let variant = GString::from(format!("{}{}", path, extension));
+        FileAccess::file_exists(&variant).then_some((variant, Some(*encoding)))
+    };
+
+    PRECOMPRESSED.iter()
+        .filter(|(encoding, _)| accepts_encoding(accept_encoding, encoding))
+        .find_map(variant)
+        .or_else(|| FileAccess::file_exists(path).then(|| (path.clone(), None)))
+        .or_else(|| PRECOMPRESSED.iter().find_map(variant))
+}
+
+// What is sent for a file: its stored bytes, or a decoded copy of them.
+enum FileBody {
+    File(Gd<FileAccess>),
+    Decoded(Vec<u8>),
+}
+
+impl FileBody {
+    fn read(&mut self, start: u64, length: u64) -> Vec<u8> {
+        match self {
+            FileBody::File(file) => read_file_range(file, start, length),
+            FileBody::Decoded(data) => {
+                let start = (start as usize).min(data.len());
+                let end = start.saturating_add(length as usize).min(data.len());
+                data[start..end].to_vec()
+            },
+        }
+    }
+}
+
+// Files packed in the PCK have no modification time, but they only change
+// with a new export, so the export's own modification time stands in for it.
+fn file_etag(path: &GString, file_size: u64, modified_time: u64) -> String {
+    if modified_time > 0 {
+        return format!("\"{:x}-{:x}\"", modified_time, file_size);
+    }
+
+    let mut hasher = DefaultHasher::new();
+    path.to_string().hash(&mut hasher);
+    format!("\"{:x}-{:x}-{:x}\"", *EXPORT_MODIFIED_TIME, file_size, hasher.finish())
+}
+
+// The latest modification time of the executable and the PCK next to it, if any.
+fn export_modified_time() -> u64 {
+    let executable = OS::singleton().get_executable_path().to_string();
+    let pck = PathBuf::from(&executable).with_extension("pck");
+    [executable, pck.to_string_lossy().into_owned()]
+        .into_iter()
+        .map(GString::from)
+        .filter(|path| FileAccess::file_exists(path))
+        .map(|path| FileAccess::get_modified_time(&path))
+        .max()
+        .unwrap_or(0)
+}
+
+fn read_file_range(file: &mut Gd<FileAccess>, start: u64, length: u64) -> Vec<u8> {
+    file.seek(start);
+
+    let mut content = Vec::with_capacity(length as usize);
+    while (content.len() as u64) < length {
+        let remaining = length - content.len() as u64;
+        let chunk = file.get_buffer(remaining.min(READ_CHUNK_SIZE) as i64);
+        if chunk.is_empty() {
+            break;
+        }
+        content.extend_from_slice(chunk.as_slice());
+    }
+    content
+}
+
+// The request's Origin, if it is one of `allowed_origins`.
+pub fn allowed_origin(request: &Request<Vec<u8>>, allowed_origins: &[String]) -> Option<String> {
+    request.headers()
+        .get(ORIGIN)
+        .and_then(|value| value.to_str().ok())
+        .map(origin_of)
+        .filter(|origin| is_origin_allowed(origin, allowed_origins))
+}
+
+// Binary channel between Godot and the page:
+// - `GET /take/<token>` hands out (and forgets) bytes queued with `WebView.send_bytes`
+// - `POST /send/<channel>` delivers the request body to `on_received`
+// Only pages from `ipc_allowed_origins` can use it, like the IPC channel.
+pub fn get_bytes_response(
+    request: Request<Vec<u8>>,
+    outgoing: &Mutex<HashMap<String, Vec<u8>>>,
+    allowed_origins: &[String],
+    on_received: impl FnOnce(String, Vec<u8>),
+) -> Response<Cow<'static, [u8]>> {
+    // cross-origin fetches always send `Origin`, the `Referer` covers the rest
+    let origin = [ORIGIN, REFERER]
+        .into_iter()
+        .find_map(|name| request.headers().get(name).and_then(|value| value.to_str().ok()))
+        .map(origin_of);
+    let Some(origin) = origin.filter(|origin| is_origin_allowed(origin, allowed_origins)) else {
+        debug_print!("[WRY Protocol] gdbytes request rejected: {}", request.uri());
+        return http::Response::builder()
+            .status(403)
+            .body(Cow::from(Vec::new()))
+            .expect("Failed to build 403 response");
+    };
+
+    let builder = http::Response::builder()
+        .header(ACCESS_CONTROL_ALLOW_ORIGIN, origin)
+        .header(VARY, "Origin");
+
+    let method = request.method().clone();
+    let path = request.uri().path().trim_start_matches('/').to_string();
+    let (action, param) = path.split_once('/').unwrap_or((path.as_str(), ""));
+
+    if method == Method::OPTIONS {
+        builder
+            .header(ACCESS_CONTROL_ALLOW_METHODS, "GET, POST, OPTIONS")
+            .header(ACCESS_CONTROL_ALLOW_HEADERS, "*")
+            .status(204)
+            .body(Cow::from(Vec::new()))
+            .expect("Failed to build 204 response")
+    } else if method == Method::GET && action == "take" {
+        let data = outgoing.lock().unwrap().remove(param);
+        match data {
+            Some(data) => builder
+                .header(CONTENT_TYPE, "application/octet-stream")
+                .status(200)
+                .body(Cow::from(data))
+                .expect("Failed to build 200 response"),
+            None => builder
+                .status(404)
+                .body(Cow::from(Vec::new()))
+                .expect("Failed to build 404 response"),
+        }
+    } else if method == Method::POST && action == "send" {
+        on_received(percent_decode(param), request.into_body());
+        builder
+            .status(204)
+            .body(Cow::from(Vec::new()))
+            .expect("Failed to build 204 response")
+    } else {
+        builder
+            .status(400)
+            .body(Cow::from(Vec::new()))
+            .expect("Failed to build 400 response")
+    }
+}
+
+// Requests to protocols added with `WebView.register_protocol`. They are
+// queued and answered from `process()`, where calling into GDScript is safe.
+pub struct ProtocolRequest {
+    pub scheme: String,
+    pub request: Request<Vec<u8>>,
+    pub responder: RequestAsyncResponder,
+}
+
+// Arguments passed to the handler: method, path, query, headers and body.
+pub fn protocol_request_args(scheme: &str, request: &Request<Vec<u8>>) -> [Variant; 5] {
+    let uri = request.uri();
+    let host = uri.host().unwrap_or_default();
+    let windows_prefix = format!("{}.", scheme);
+    let host = host.strip_prefix(&windows_prefix).unwrap_or(host);
+    let path = percent_decode(&format!("{}{}", host, uri.path()));
+
+    let mut query = Dictionary::new();
+    for pair in uri.query().unwrap_or_default().split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        query.set(percent_decode(&key.replace('+', " ")), percent_decode(&value.replace('+', " ")));
+    }
+
+    let mut headers = Dictionary::new();
+    for (name, value) in request.headers() {
+        headers.set(name.as_str(), value.to_str().unwrap_or_default());
+    }
+
+    [
+        request.method().as_str().to_variant(),
+        path.to_variant(),
+        query.to_variant(),
+        headers.to_variant(),
+        PackedByteArray::from(request.body().as_slice()).to_variant(),
+    ]
+}
+
+// Handlers return a Dictionary with optional `status` (200), `headers` and
+// `body` (PackedByteArray or String) keys.
+// `origin` is the request's allowed Origin, see `allowed_origin`.
+pub fn protocol_response(result: &Variant, origin: Option<&str>) -> Response<Cow<'static, [u8]>> {
+    let Ok(response) = result.try_to::<Dictionary>() else {
+        return protocol_error(500, "Protocol handler must return a Dictionary");
+    };
+
+    let status = response.get("status").and_then(|status| status.try_to::<i64>().ok()).unwrap_or(200);
+    let headers = response.get("headers").and_then(|headers| headers.try_to::<Dictionary>().ok()).unwrap_or_default();
+    let body = match response.get("body") {
+        None => Vec::new(),
+        Some(body) if body.is_nil() => Vec::new(),
+        Some(body) => match body.try_to::<PackedByteArray>() {
+            Ok(bytes) => bytes.as_slice().to_vec(),
+            Err(_) => body.to_string().into_bytes(),
+        },
+    };
+
+    // pages are served from another scheme, so fetch() would be blocked by CORS otherwise.
+    // handlers opt into other origins by setting the header themselves
+    let mut builder = http::Response::builder().status(u16::try_from(status).unwrap_or(500));
+    let has_allow_origin = headers.keys_array()
+        .iter_shared()
+        .any(|name| name.to_string().eq_ignore_ascii_case(ACCESS_CONTROL_ALLOW_ORIGIN.as_str()));
+    if let (false, Some(origin)) = (has_allow_origin, origin) {
+        builder = builder
+            .header(ACCESS_CONTROL_ALLOW_ORIGIN, origin)
+            .header(VARY, "Origin");
+    }
+    for (name, value) in headers.iter_shared() {
+        builder = builder.header(name.to_string(), value.to_string());
+    }
+
+    builder
+        .body(Cow::from(body))
+        .unwrap_or_else(|error| protocol_error(500, &format!("Invalid protocol response: {}", error)))
+}
+
+pub fn protocol_error(status: u16, message: &str) -> Response<Cow<'static, [u8]>> {
+    http::Response::builder()
+        .header(CONTENT_TYPE, "text/plain")
+        .header(ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .status(status)
+        .body(Cow::from(message.as_bytes().to_vec()))
+        .expect("Failed to build error response")
 }
 
 lazy_static! {
+    static ref EXPORT_MODIFIED_TIME: u64 = export_modified_time();
+
     static ref MIME_TYPES: HashMap<&'static str, &'static str> = HashMap::from([
         // https://developer.mozilla.org/en-US/docs/Web/HTTP/Guides/MIME_types/Common_types
         ("aac", "audio/aac"),
@@ -165,6 +731,9 @@ lazy_static! {
         ("docx", "application/vnd.openxmlformats-officedocument.wordprocessingml.document"),
         ("eot", "application/vnd.ms-fontobject"),
         ("epub", "application/epub+zip"),
+        ("flac", "audio/flac"),
+        ("glb", "model/gltf-binary"),
+        ("gltf", "model/gltf+json"),
         ("gz", "application/gzip"),
         ("gif", "image/gif"),
         ("html", "text/html"),
@@ -176,7 +745,11 @@ lazy_static! {
         ("jpg", "image/jpeg"),
         ("js", "text/javascript"),
         ("json", "application/json"),
+        ("jsonc", "application/json"),
         ("jsonld", "application/ld+json"),
+        ("ktx2", "image/ktx2"),
+        ("m4a", "audio/mp4"),
+        ("map", "application/json"),
         ("midi", "audio/midi"),
         ("mid", "audio/midi"),
         ("mjs", "text/javascript"),
@@ -212,6 +785,7 @@ lazy_static! {
         ("wasm", "application/wasm"),
         ("weba", "audio/webm"),
         ("webm", "video/webm"),
+        ("webmanifest", "application/manifest+json"),
         ("webp", "image/webp"),
         ("woff", "font/woff"),
         ("woff2", "font/woff2"),
@@ -0,0 +1,327 @@
+// Byte range requests (RFC 7233) for files served by the `res` protocol.
+
+use crate::cache::format_http_date;
+
+// Requests with more ranges than this are answered with the whole file.
+const MAX_RANGES: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    // inclusive, like in `Content-Range`
+    pub end: u64,
+}
+
+impl ByteRange {
+    pub fn length(&self) -> u64 {
+        self.end - self.start + 1
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RangeRequest {
+    // no usable Range header, the whole file is sent with 200
+    Full,
+    Partial(Vec<ByteRange>),
+    Unsatisfiable,
+}
+
+// Ranges are capped to `max_length` bytes, browsers ask for the rest while
+// playing media. Overlapping and adjacent ranges are merged first, so a
+// request can't make the same bytes be read over and over. Invalid headers
+// are ignored as allowed by the RFC, while valid headers where no range
+// overlaps the file are unsatisfiable.
+pub fn parse_range(header: &str, file_size: u64, max_length: u64) -> RangeRequest {
+    let Some((unit, specs)) = header.split_once('=') else {
+        return RangeRequest::Full;
+    };
+    if !unit.trim().eq_ignore_ascii_case("bytes") {
+        return RangeRequest::Full;
+    }
+
+    let specs: Vec<&str> = specs.split(',').map(str::trim).filter(|spec| !spec.is_empty()).collect();
+    if specs.is_empty() || specs.len() > MAX_RANGES {
+        return RangeRequest::Full;
+    }
+
+    let mut ranges = Vec::new();
+    for spec in specs {
+        let Some((start, end)) = spec.split_once('-') else {
+            return RangeRequest::Full;
+        };
+
+        if start.is_empty() {
+            // "bytes=-n" asks for the last n bytes
+            let Ok(suffix) = end.parse::<u64>() else {
+                return RangeRequest::Full;
+            };
+            if suffix > 0 && file_size > 0 {
+                ranges.push(ByteRange { start: file_size.saturating_sub(suffix), end: file_size - 1 });
+            }
+            continue;
+        }
+
+        let Ok(start) = start.parse::<u64>() else {
+            return RangeRequest::Full;
+        };
+        let end = if end.is_empty() {
+            u64::MAX
+        } else {
+            match end.parse::<u64>() {
+                Ok(end) if end >= start => end,
+                _ => return RangeRequest::Full,
+            }
+        };
+
+        if start < file_size {
+            ranges.push(ByteRange { start, end: end.min(file_size - 1) });
+        }
+    }
+
+    if ranges.is_empty() {
+        return RangeRequest::Unsatisfiable;
+    }
+
+    let ranges = merge_ranges(ranges)
+        .into_iter()
+        .map(|range| ByteRange { start: range.start, end: range.end.min(range.start.saturating_add(max_length.max(1) - 1)) })
+        .collect();
+    RangeRequest::Partial(ranges)
+}
+
+// Merges overlapping and adjacent ranges (RFC 7233 §6.1), others keep their order.
+fn merge_ranges(ranges: Vec<ByteRange>) -> Vec<ByteRange> {
+    let mut merged: Vec<ByteRange> = Vec::with_capacity(ranges.len());
+    for mut range in ranges {
+        // the range can join several earlier ones, it takes the place of the first
+        let mut position = None;
+        let mut i = 0;
+        while i < merged.len() {
+            let other = merged[i];
+            if other.start <= range.end.saturating_add(1) && range.start <= other.end.saturating_add(1) {
+                range = ByteRange { start: range.start.min(other.start), end: range.end.max(other.end) };
+                merged.remove(i);
+                position.get_or_insert(i);
+            } else {
+                i += 1;
+            }
+        }
+        merged.insert(position.unwrap_or(merged.len()), range);
+    }
+    merged
+}
+
+// `If-Range` makes the Range conditional: when the validator doesn't match
+// the current representation, the whole file must be sent instead. Only
+// strong entity tags can match, and dates must be the exact `Last-Modified`.
+pub fn if_range_matches(if_range: &str, etag: Option<&str>, last_modified: Option<u64>) -> bool {
+    let if_range = if_range.trim();
+    if if_range.starts_with("W/") {
+        return false;
+    }
+
+    if if_range.starts_with('"') {
+        return etag.is_some_and(|etag| !etag.starts_with("W/") && if_range == etag);
+    }
+    last_modified.is_some_and(|last_modified| if_range == format_http_date(last_modified))
+}
+
+// Body of a `multipart/byteranges` response, sent with
+// `Content-Type: multipart/byteranges; boundary=<boundary>`.
+pub fn multipart_byteranges(
+    parts: &[(ByteRange, Vec<u8>)],
+    content_type: &str,
+    file_size: u64,
+    boundary: &str,
+) -> Vec<u8> {
+    let mut body = Vec::new();
+    for (range, content) in parts {
+        body.extend_from_slice(
+            format!(
+                "\r\n--{}\r\nContent-Type: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+                boundary, content_type, range.start, range.end, file_size
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(content);
+    }
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX: u64 = 1000;
+
+    fn range(start: u64, end: u64) -> ByteRange {
+        ByteRange { start, end }
+    }
+
+    #[test]
+    fn single_range() {
+        assert_eq!(parse_range("bytes=0-499", 10000, MAX), RangeRequest::Partial(vec![range(0, 499)]));
+        assert_eq!(parse_range("bytes=500-999", 10000, MAX), RangeRequest::Partial(vec![range(500, 999)]));
+        assert_eq!(parse_range("bytes=0-0", 10000, MAX), RangeRequest::Partial(vec![range(0, 0)]));
+    }
+
+    #[test]
+    fn end_past_file_size_is_clamped() {
+        assert_eq!(parse_range("bytes=9500-20000", 10000, MAX), RangeRequest::Partial(vec![range(9500, 9999)]));
+    }
+
+    #[test]
+    fn open_ended_range() {
+        assert_eq!(parse_range("bytes=9500-", 10000, MAX), RangeRequest::Partial(vec![range(9500, 9999)]));
+    }
+
+    #[test]
+    fn open_ended_range_is_capped() {
+        assert_eq!(parse_range("bytes=0-", 10000, MAX), RangeRequest::Partial(vec![range(0, 999)]));
+        assert_eq!(parse_range("bytes=0-", 10000, 0), RangeRequest::Partial(vec![range(0, 0)]));
+    }
+
+    #[test]
+    fn every_range_is_capped() {
+        assert_eq!(parse_range("bytes=0-4999", 10000, MAX), RangeRequest::Partial(vec![range(0, 999)]));
+        assert_eq!(parse_range("bytes=-5000", 10000, MAX), RangeRequest::Partial(vec![range(5000, 5999)]));
+        assert_eq!(
+            parse_range("bytes=0-1999,5000-", 10000, MAX),
+            RangeRequest::Partial(vec![range(0, 999), range(5000, 5999)])
+        );
+    }
+
+    #[test]
+    fn suffix_range() {
+        assert_eq!(parse_range("bytes=-500", 10000, MAX), RangeRequest::Partial(vec![range(9500, 9999)]));
+    }
+
+    #[test]
+    fn suffix_range_longer_than_file() {
+        assert_eq!(parse_range("bytes=-500", 100, MAX), RangeRequest::Partial(vec![range(0, 99)]));
+    }
+
+    #[test]
+    fn multiple_ranges_keep_their_order() {
+        assert_eq!(
+            parse_range("bytes=500-599, 0-99,-10", 10000, MAX),
+            RangeRequest::Partial(vec![range(500, 599), range(0, 99), range(9990, 9999)])
+        );
+    }
+
+    #[test]
+    fn overlapping_and_adjacent_ranges_are_merged() {
+        assert_eq!(parse_range("bytes=0-99,50-149", 10000, MAX), RangeRequest::Partial(vec![range(0, 149)]));
+        assert_eq!(parse_range("bytes=0-99,100-199", 10000, MAX), RangeRequest::Partial(vec![range(0, 199)]));
+        assert_eq!(parse_range("bytes=0-99,0-99,0-99", 10000, MAX), RangeRequest::Partial(vec![range(0, 99)]));
+        assert_eq!(parse_range("bytes=-100,9000-", 10000, MAX), RangeRequest::Partial(vec![range(9000, 9999)]));
+    }
+
+    #[test]
+    fn merged_ranges_keep_the_first_position() {
+        assert_eq!(
+            parse_range("bytes=500-599,0-99,200-299,100-199", 10000, MAX),
+            RangeRequest::Partial(vec![range(500, 599), range(0, 299)])
+        );
+    }
+
+    #[test]
+    fn merged_ranges_are_capped() {
+        assert_eq!(
+            parse_range(&format!("bytes={}", ["0-99", "100-199", "200-299"].repeat(4).join(",")), 10000, 150),
+            RangeRequest::Partial(vec![range(0, 149)])
+        );
+    }
+
+    #[test]
+    fn unsatisfiable_ranges_are_dropped() {
+        assert_eq!(
+            parse_range("bytes=0-9,20000-30000", 10000, MAX),
+            RangeRequest::Partial(vec![range(0, 9)])
+        );
+    }
+
+    #[test]
+    fn unsatisfiable() {
+        assert_eq!(parse_range("bytes=10000-", 10000, MAX), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range("bytes=20000-30000", 10000, MAX), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-0", 10000, MAX), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-", 0, MAX), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-5", 0, MAX), RangeRequest::Unsatisfiable);
+    }
+
+    #[test]
+    fn invalid_headers_are_ignored() {
+        assert_eq!(parse_range("bytes=", 10000, MAX), RangeRequest::Full);
+        assert_eq!(parse_range("bytes", 10000, MAX), RangeRequest::Full);
+        assert_eq!(parse_range("bytes=abc", 10000, MAX), RangeRequest::Full);
+        assert_eq!(parse_range("bytes=5-1", 10000, MAX), RangeRequest::Full);
+        assert_eq!(parse_range("bytes=0-9,x-y", 10000, MAX), RangeRequest::Full);
+        assert_eq!(parse_range("bytes=--5", 10000, MAX), RangeRequest::Full);
+        assert_eq!(parse_range("bytes=ü-5", 10000, MAX), RangeRequest::Full);
+    }
+
+    #[test]
+    fn other_units_are_ignored() {
+        assert_eq!(parse_range("items=0-5", 10000, MAX), RangeRequest::Full);
+    }
+
+    #[test]
+    fn unit_is_case_insensitive() {
+        assert_eq!(parse_range("Bytes=0-5", 10000, MAX), RangeRequest::Partial(vec![range(0, 5)]));
+    }
+
+    #[test]
+    fn too_many_ranges_are_ignored() {
+        let header = format!("bytes={}", ["0-0"; MAX_RANGES + 1].join(","));
+        assert_eq!(parse_range(&header, 10000, MAX), RangeRequest::Full);
+    }
+
+    #[test]
+    fn if_range_with_matching_etag() {
+        assert!(if_range_matches("\"abc\"", Some("\"abc\""), None));
+    }
+
+    #[test]
+    fn if_range_with_other_etag() {
+        assert!(!if_range_matches("\"abc\"", Some("\"def\""), None));
+        assert!(!if_range_matches("\"abc\"", None, Some(0)));
+    }
+
+    #[test]
+    fn if_range_with_weak_etag() {
+        assert!(!if_range_matches("W/\"abc\"", Some("W/\"abc\""), None));
+        assert!(!if_range_matches("\"abc\"", Some("W/\"abc\""), None));
+    }
+
+    #[test]
+    fn if_range_with_date() {
+        // Wed, 21 Oct 2015 07:28:00 GMT
+        let modified = 1445412480;
+        assert!(if_range_matches("Wed, 21 Oct 2015 07:28:00 GMT", Some("\"abc\""), Some(modified)));
+        assert!(if_range_matches(" Wed, 21 Oct 2015 07:28:00 GMT ", None, Some(modified)));
+        assert!(!if_range_matches("Wed, 21 Oct 2015 07:28:01 GMT", None, Some(modified)));
+        assert!(!if_range_matches("Wed, 21 Oct 2015 07:28:00 GMT", Some("\"abc\""), None));
+        assert!(!if_range_matches("Wednesday, 21-Oct-15 07:28:00 GMT", None, Some(modified)));
+        assert!(!if_range_matches("Wed, 21 Oct 2015 07:28", None, Some(modified)));
+    }
+
+    #[test]
+    fn multipart_body() {
+        let parts = [(range(0, 2), b"abc".to_vec()), (range(8, 9), b"ij".to_vec())];
+        let body = multipart_byteranges(&parts, "text/plain", 10, "BOUNDARY");
+        assert_eq!(
+            String::from_utf8(body).unwrap(),
+            "\r\n--BOUNDARY\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-2/10\r\n\r\nabc\
+             \r\n--BOUNDARY\r\nContent-Type: text/plain\r\nContent-Range: bytes 8-9/10\r\n\r\nij\
+             \r\n--BOUNDARY--\r\n"
+        );
+    }
+
+    #[test]
+    fn byte_range_length() {
+        assert_eq!(range(0, 0).length(), 1);
+        assert_eq!(range(10, 19).length(), 10);
+    }
+}
@@ -0,0 +1,50 @@
+// Schemes accepted by `register_protocol`.
+
+// Schemes the webview or Godot WRY already handle, a custom protocol would
+// shadow them or never be reached.
+const RESERVED_SCHEMES: [&str; 11] = [
+    "res", "gdbytes", "http", "https", "file", "data", "blob", "about", "ws", "wss", "javascript",
+];
+
+// `ALPHA *( ALPHA / DIGIT / "+" / "-" / "." )` from RFC 3986 §3.1.
+pub fn is_valid_scheme(scheme: &str) -> bool {
+    let mut chars = scheme.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
+
+// `scheme` is lowercase.
+pub fn is_reserved_scheme(scheme: &str) -> bool {
+    RESERVED_SCHEMES.contains(&scheme)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_schemes() {
+        assert!(is_valid_scheme("app"));
+        assert!(is_valid_scheme("my-app+v2.local"));
+        assert!(is_valid_scheme("A1"));
+    }
+
+    #[test]
+    fn invalid_schemes() {
+        assert!(!is_valid_scheme(""));
+        assert!(!is_valid_scheme("1app"));
+        assert!(!is_valid_scheme("-app"));
+        assert!(!is_valid_scheme("my app"));
+        assert!(!is_valid_scheme("app:"));
+        assert!(!is_valid_scheme("app/x"));
+        assert!(!is_valid_scheme("äpp"));
+    }
+
+    #[test]
+    fn reserved_schemes() {
+        for scheme in ["res", "gdbytes", "http", "https", "file", "data", "blob", "about", "ws"] {
+            assert!(is_reserved_scheme(scheme));
+        }
+        assert!(!is_reserved_scheme("app"));
+    }
+}
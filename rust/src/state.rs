@@ -0,0 +1,86 @@
+use serde_json::{Map, Value};
+
+// State paths are dot-separated keys, e.g. `player.inventory.0.count`.
+// Numeric keys index into existing arrays, any other key into objects. Like
+// `applyState` in JS, setting past the end of an array pads it with nulls.
+
+pub fn get_path<'a>(root: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .filter(|key| !key.is_empty())
+        .try_fold(root, |node, key| match node {
+            Value::Object(map) => map.get(key),
+            Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        })
+}
+
+pub fn set_path(root: &mut Value, path: &str, value: Value) {
+    let mut node = root;
+    for key in path.split('.').filter(|key| !key.is_empty()) {
+        let index = key.parse::<usize>().ok().filter(|_| node.is_array());
+
+        node = match index {
+            Some(i) => {
+                let items = node.as_array_mut().unwrap();
+                if i >= items.len() {
+                    items.resize(i + 1, Value::Null);
+                }
+                &mut items[i]
+            },
+            None => {
+                if !node.is_object() {
+                    *node = Value::Object(Map::new());
+                }
+                &mut node[key]
+            },
+        };
+    }
+    *node = value;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn gets_nested_keys_and_indexes() {
+        let state = json!({ "player": { "inventory": [{ "count": 3 }] } });
+        assert_eq!(get_path(&state, "player.inventory.0.count"), Some(&json!(3)));
+        assert_eq!(get_path(&state, ""), Some(&state));
+        assert_eq!(get_path(&state, "player.inventory.1"), None);
+        assert_eq!(get_path(&state, "player.inventory.count"), None);
+        assert_eq!(get_path(&state, "player.health"), None);
+    }
+
+    #[test]
+    fn sets_nested_keys_creating_objects() {
+        let mut state = json!({});
+        set_path(&mut state, "player.health", json!(100));
+        assert_eq!(state, json!({ "player": { "health": 100 } }));
+
+        set_path(&mut state, "player", json!(1));
+        set_path(&mut state, "player.score.0", json!(2));
+        assert_eq!(state, json!({ "player": { "score": { "0": 2 } } }));
+    }
+
+    #[test]
+    fn sets_array_indexes() {
+        let mut state = json!({ "items": [1, 2] });
+        set_path(&mut state, "items.0", json!(3));
+        assert_eq!(state, json!({ "items": [3, 2] }));
+
+        set_path(&mut state, "items.2", json!(4));
+        assert_eq!(state, json!({ "items": [3, 2, 4] }));
+
+        set_path(&mut state, "items.5", json!(5));
+        assert_eq!(state, json!({ "items": [3, 2, 4, null, null, 5] }));
+    }
+
+    #[test]
+    fn replaces_whole_state_with_empty_path() {
+        let mut state = json!({ "a": 1 });
+        set_path(&mut state, "", json!([1]));
+        assert_eq!(state, json!([1]));
+    }
+}
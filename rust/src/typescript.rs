@@ -0,0 +1,118 @@
+// Declarations for the JavaScript API injected by `bridge.rs`. Names known at
+// runtime (registered functions, channels, bridged signals) are emitted as
+// literal types, so front-end builds can catch typos.
+#[derive(Default)]
+pub struct TypeScriptNames {
+    pub functions: Vec<String>,
+    pub incoming_channels: Vec<String>,
+    pub outgoing_channels: Vec<String>,
+    pub dom_events: Vec<String>,
+}
+
+pub fn generate_declarations(names: &TypeScriptNames) -> String {
+    let functions = if names.functions.is_empty() {
+        "  [name: string]: (...args: any[]) => Promise<any>;\n".to_string()
+    } else {
+        sorted(&names.functions)
+            .iter()
+            .map(|name| format!("  {}: (...args: any[]) => Promise<any>;\n", quote(name)))
+            .collect()
+    };
+
+    let dom_events: String = sorted(&names.dom_events)
+        .iter()
+        .map(|name| format!("    {}: CustomEvent<any[]>;\n", quote(name)))
+        .collect();
+
+    format!(
+        r#"// Generated by Godot WRY, do not edit.
+
+export interface GodotFunctions {{
+{functions}}}
+
+/** Channels Godot sends to with `emit_event()`. */
+export type GodotIncomingChannel = {incoming};
+
+/** Channels Godot listens to with `subscribe()` or `event_received`. */
+export type GodotOutgoingChannel = {outgoing};
+
+export interface GodotStateChange {{
+  path: string;
+  value: any;
+  source: "godot" | "page";
+}}
+
+export interface GodotState extends EventTarget {{
+  get(path?: string): any;
+  set(path: string, value: any): void;
+  on(path: string, handler: (value: any, change: GodotStateChange) => void): () => void;
+}}
+
+/** Transport for `ipc_protocol = IPC_PROTOCOL_JSON_RPC`. */
+export interface GodotJsonRpc {{
+  send(message: string | object): void;
+  onResponse(handler: (response: any) => void): () => void;
+}}
+
+export interface Godot {{
+  call(method: string, ...args: any[]): Promise<any>;
+  functions: GodotFunctions;
+  on(channel: GodotIncomingChannel, handler: (payload: any) => void): () => void;
+  off(channel: GodotIncomingChannel, handler: (payload: any) => void): void;
+  emit(channel: GodotOutgoingChannel, payload?: any): void;
+  sendBytes(channel: string, data: BodyInit): Promise<void>;
+  onBytes(channel: string, handler: (data: ArrayBuffer) => void): () => void;
+  state: GodotState;
+  jsonRpc: GodotJsonRpc;
+}}
+
+/** Messages posted by the page when `forward_input_events` is enabled. */
+export type GodotInputMessage =
+  | {{ type: "_mouse_move"; x: number; y: number; movementX: number; movementY: number; button: number }}
+  | {{ type: "_mouse_down" | "_mouse_up"; x: number; y: number; button: number }}
+  | {{ type: "_mouse_wheel"; x: number; y: number; deltaX: number; deltaY: number; shift: boolean; ctrl: boolean; alt: boolean; meta: boolean }}
+  | {{ type: "_key_down" | "_key_up"; key: string; code: string; keyCode: number; shift: boolean; ctrl: boolean; alt: boolean; meta: boolean }};
+
+export interface GodotIpc {{
+  postMessage(message: string): void;
+}}
+
+declare global {{
+  interface Window {{
+    godot: Godot;
+    ipc: GodotIpc;
+  }}
+
+  var godot: Godot;
+  var ipc: GodotIpc;
+
+  interface DocumentEventMap {{
+    /** Sent by Godot with `post_message()`. */
+    message: CustomEvent<any>;
+{dom_events}  }}
+}}
+"#,
+        functions = functions,
+        incoming = union(&names.incoming_channels),
+        outgoing = union(&names.outgoing_channels),
+        dom_events = dom_events,
+    )
+}
+
+fn sorted(names: &[String]) -> Vec<&String> {
+    let mut names: Vec<&String> = names.iter().collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+fn quote(name: &str) -> String {
+    serde_json::Value::String(name.to_string()).to_string()
+}
+
+fn union(names: &[String]) -> String {
+    if names.is_empty() {
+        return "string".to_string();
+    }
+    sorted(names).iter().map(|name| quote(name)).collect::<Vec<_>>().join(" | ")
+}